@@ -0,0 +1,138 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use std::f32::consts::TAU;
+
+use crate::{Orb, OrbBorder, Score, Sizes, controls::Controls, theme::{Paint, Theme}};
+
+const DASHES: usize = 12;
+// radians per second the dashed outline turns
+const DASH_SPIN: f32 = 0.8;
+// how much of the orb body gives way to border when the goal is reached
+const BORDER_GROWTH: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorVision {
+    Typical,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl ColorVision {
+    pub fn next(self) -> Self {
+        match self {
+            ColorVision::Typical => ColorVision::Deuteranopia,
+            ColorVision::Deuteranopia => ColorVision::Protanopia,
+            ColorVision::Protanopia => ColorVision::Tritanopia,
+            ColorVision::Tritanopia => ColorVision::Typical,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Accessibility {
+    pub color_vision: ColorVision,
+    pub high_contrast: bool,
+    // orb borders thicken toward the goal and get a spinning dashed outline once orbs
+    // can be hunted, the target pickup ring is drawn larger
+    pub shape_cues: bool,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Accessibility {
+            color_vision: ColorVision::Typical,
+            high_contrast: false,
+            shape_cues: true,
+        }
+    }
+}
+
+// Holds the dashes of one orb, rotated as a whole.
+#[derive(Component)]
+pub struct DashRing;
+
+pub fn accessibility_keys(
+    input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
+    mut accessibility: ResMut<Accessibility>,
+) {
+    if input.just_pressed(controls.color_vision) {
+        accessibility.color_vision = accessibility.color_vision.next();
+    }
+    if input.just_pressed(controls.high_contrast) {
+        accessibility.high_contrast = !accessibility.high_contrast;
+    }
+    if input.just_pressed(controls.shape_cues) {
+        accessibility.shape_cues = !accessibility.shape_cues;
+    }
+}
+
+pub fn orb_cues(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    accessibility: Res<Accessibility>,
+    score: Res<Score>,
+    size: Res<Sizes>,
+    theme: Res<Theme>,
+    new_orbs: Query<Entity, Added<Orb>>,
+    mut body_query: Query<&mut Mesh2dHandle, With<Orb>>,
+    mut ring_query: Query<&mut Visibility, With<DashRing>>,
+) {
+    let progress = score.progress();
+    let killable = score.hunting();
+
+    for orb in new_orbs.iter() {
+        let radius = size.orb * 1.12;
+        let dash = shape::Quad::new(Vec2::new(TAU * radius / (DASHES * 2) as f32, size.orb * 0.08));
+        let dash_mesh: Mesh2dHandle = meshes.add(dash.into()).into();
+
+        commands.entity(orb).with_children(|parent| {
+            parent.spawn(SpatialBundle {
+                visibility: Visibility { is_visible: accessibility.shape_cues && killable },
+                ..default()
+            })
+            .insert(DashRing)
+            .with_children(|ring| {
+                for i in 0..DASHES {
+                    let angle = TAU * i as f32 / DASHES as f32;
+                    ring.spawn(MaterialMesh2dBundle {
+                        mesh: dash_mesh.clone(),
+                        material: materials.add(ColorMaterial::from(theme.orb_border(progress))),
+                        transform: Transform::from_translation(Vec3::new(angle.cos() * radius, angle.sin() * radius, -1.))
+                            .with_rotation(Quat::from_rotation_z(angle + TAU / 4.)),
+                        ..default()
+                    })
+                    .insert(OrbBorder)
+                    .insert(Paint::OrbBorder);
+                }
+            });
+        });
+    }
+
+    if !score.is_changed() && !accessibility.is_changed() {
+        return;
+    }
+
+    // the black body shrinks so the colored border reads thicker as the goal gets closer
+    let body = if accessibility.shape_cues { 0.95 - BORDER_GROWTH * progress } else { 0.95 };
+    let body_mesh: Mesh2dHandle = meshes.add(shape::Circle::new(size.orb * body).into()).into();
+    for mut mesh in body_query.iter_mut() {
+        *mesh = body_mesh.clone();
+    }
+    for mut visibility in ring_query.iter_mut() {
+        visibility.is_visible = accessibility.shape_cues && killable;
+    }
+}
+
+pub fn spin_dashes(
+    time: Res<Time>,
+    mut query: Query<&mut Transform, With<DashRing>>,
+) {
+    for mut transform in query.iter_mut() {
+        transform.rotate_z(DASH_SPIN * time.delta_seconds());
+    }
+}
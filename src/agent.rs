@@ -0,0 +1,415 @@
+use bevy::{
+    app::AppExit,
+    ecs::system::SystemParam,
+    prelude::*,
+    time::TimeUpdateStrategy,
+    utils::{Duration, Instant},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    process,
+};
+
+use crate::{
+    Collider, GameState, GameTime, IDmin, Orb, PickID, Pickup, Player, Score, Sizes, Speed,
+    coop::Down,
+    modes::{FixedSeed, GameMode, Players, RunConfig},
+};
+
+const DEFAULT_PORT: u16 = 7878;
+// simulated seconds per frame, the same however long the agent thinks
+const STEP: f32 = 1. / 60.;
+// reward taken off for each life lost, on top of the points gained
+const LIFE_COST: f32 = 100.;
+// idle, then clockwise from up
+const ACTIONS: [Vec2; 9] = [
+    Vec2::ZERO,
+    Vec2::new(0., 1.),
+    Vec2::new(1., 1.),
+    Vec2::new(1., 0.),
+    Vec2::new(1., -1.),
+    Vec2::new(0., -1.),
+    Vec2::new(-1., -1.),
+    Vec2::new(-1., 0.),
+    Vec2::new(-1., 1.),
+];
+
+enum Transport {
+    Stdio,
+    Tcp {
+        listener: TcpListener,
+        stream: Option<BufReader<TcpStream>>,
+    },
+}
+
+enum Phase {
+    // blocked on the next command
+    Waiting,
+    // frames still to run for the current step
+    Stepping(u32),
+    // back through the title, answered once the new run is up
+    Resetting,
+}
+
+// Requests, one JSON object per line.
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Command {
+    Reset {
+        seed: Option<u64>,
+        mode: Option<GameMode>,
+        players: Option<Players>,
+    },
+    Step {
+        action: usize,
+        // frames to hold the action for
+        #[serde(default = "one")]
+        repeat: u32,
+    },
+    Observe,
+    Close,
+}
+
+fn one() -> u32 {
+    1
+}
+
+#[derive(Serialize)]
+struct PlayerView {
+    seat: usize,
+    x: f32,
+    y: f32,
+    radius: f32,
+    lives: u32,
+    pickups: i32,
+    points: i32,
+    down: bool,
+}
+
+#[derive(Serialize)]
+struct OrbView {
+    x: f32,
+    y: f32,
+    radius: f32,
+}
+
+#[derive(Serialize)]
+struct PickupView {
+    number: i32,
+    x: f32,
+    y: f32,
+    radius: f32,
+}
+
+// Positions are in world units, the origin in the middle of a width by height screen.
+#[derive(Serialize)]
+struct Observation {
+    state: GameState,
+    width: f32,
+    height: f32,
+    time: f32,
+    goal: i32,
+    points: i32,
+    hunting: bool,
+    hunter: Option<usize>,
+    orb_speed: f32,
+    // the pickup the guide points at, -1 for none
+    target: i32,
+    players: Vec<PlayerView>,
+    orbs: Vec<OrbView>,
+    pickups: Vec<PickupView>,
+}
+
+#[derive(Serialize)]
+struct Reply {
+    observation: Observation,
+    reward: f32,
+    done: bool,
+}
+
+// Everything an observation is read from, one parameter so serve_agent stays under bevy's limit.
+#[derive(SystemParam)]
+pub(crate) struct Observed<'w, 's> {
+    score: Res<'w, Score>,
+    speed: Res<'w, Speed>,
+    size: Res<'w, Sizes>,
+    time: Res<'w, GameTime>,
+    min_id: Res<'w, IDmin>,
+    windows: Res<'w, Windows>,
+    player_query: Query<'w, 's, (&'static Transform, &'static Player, Option<&'static Down>)>,
+    orb_query: Query<'w, 's, (&'static Transform, &'static Collider), With<Orb>>,
+    pick_query: Query<'w, 's, (&'static Transform, &'static PickID), With<Pickup>>,
+}
+
+impl Observed<'_, '_> {
+    fn observe(&self, state: &GameState) -> Observation {
+        let window = self.windows.get_primary().unwrap();
+        let mut players: Vec<_> = self.player_query.iter()
+            .map(|(transform, player, down)| PlayerView {
+                seat: player.seat,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                radius: self.size.player,
+                lives: player.lives,
+                pickups: player.pickups,
+                points: player.points,
+                down: down.is_some(),
+            })
+            .collect();
+        players.sort_by_key(|player| player.seat);
+        let mut pickups: Vec<_> = self.pick_query.iter()
+            .map(|(transform, pick_id)| PickupView {
+                number: pick_id.number,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                radius: self.size.pickup,
+            })
+            .collect();
+        pickups.sort_by_key(|pickup| pickup.number);
+        Observation {
+            state: state.clone(),
+            width: window.width(),
+            height: window.height(),
+            time: self.time.value,
+            goal: self.score.goal,
+            points: self.score.points,
+            hunting: self.score.hunting(),
+            hunter: self.score.hunter,
+            orb_speed: self.speed.orb,
+            target: self.min_id.value,
+            players,
+            orbs: self.orb_query.iter()
+                .map(|(transform, collider)| OrbView {
+                    x: transform.translation.x,
+                    y: transform.translation.y,
+                    radius: collider.0,
+                })
+                .collect(),
+            pickups,
+        }
+    }
+
+    fn lives(&self) -> u32 {
+        self.player_query.iter().map(|(_, player, _)| player.lives).sum()
+    }
+}
+
+// The game as a reinforcement-learning environment, driven over stdin/stdout or a local TCP port.
+// The reward is the points gained over a step, less LIFE_COST per life lost; a run is done on any results screen.
+#[derive(Resource)]
+pub struct AgentEnv {
+    transport: Option<Transport>,
+    phase: Phase,
+    action: Vec2,
+    // the simulated clock, picked up from the real one on the first frame
+    clock: Option<Instant>,
+    // where the current step started from
+    points: i32,
+    lives: u32,
+}
+
+impl AgentEnv {
+    // --env stdio, or --env [port] for 127.0.0.1
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let transport = args.iter().position(|arg| arg == "--env").map(|at| match args.get(at + 1) {
+            Some(arg) if arg == "stdio" => Transport::Stdio,
+            arg => {
+                let port = arg.and_then(|port| port.parse().ok()).unwrap_or(DEFAULT_PORT);
+                // no agent could ever reach a game that carried on, and this runs before the logger is up
+                let listener = TcpListener::bind(("127.0.0.1", port)).unwrap_or_else(|e| {
+                    eprintln!("error: could not listen for an agent on port {}: {}", port, e);
+                    process::exit(1);
+                });
+                Transport::Tcp { listener, stream: None }
+            }
+        });
+        AgentEnv {
+            transport,
+            phase: Phase::Waiting,
+            action: Vec2::ZERO,
+            clock: None,
+            points: 0,
+            lives: 0,
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    // stdout carries the replies, nothing else may write there
+    pub fn on_stdio(&self) -> bool {
+        matches!(self.transport, Some(Transport::Stdio))
+    }
+
+    // the agent plays the first seat
+    pub fn input_for(&self, seat: usize) -> Option<Vec2> {
+        if self.active() && seat == 0 { Some(self.action) } else { None }
+    }
+
+    // None once stdin closes, a TCP agent that hangs up is replaced by the next to connect
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.transport.as_mut()? {
+            Transport::Stdio => match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(line),
+            },
+            Transport::Tcp { listener, stream } => loop {
+                let reader = match stream {
+                    Some(reader) => reader,
+                    None => {
+                        if let Ok(address) = listener.local_addr() {
+                            info!("waiting for an agent on {}", address);
+                        }
+                        let (socket, from) = listener.accept().ok()?;
+                        info!("agent connected from {}", from);
+                        stream.insert(BufReader::new(socket))
+                    }
+                };
+                line.clear();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => {
+                        info!("agent disconnected");
+                        *stream = None;
+                    }
+                    Ok(_) => return Some(line),
+                }
+            },
+        }
+    }
+
+    fn send(&mut self, reply: &impl Serialize) {
+        let mut text = serde_json::to_string(reply).unwrap();
+        text.push('\n');
+        let result = match &mut self.transport {
+            Some(Transport::Stdio) => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+            }
+            Some(Transport::Tcp { stream: Some(reader), .. }) => reader.get_mut().write_all(text.as_bytes()),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            warn!("could not answer the agent: {}", e);
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.send(&serde_json::json!({ "error": message }));
+    }
+}
+
+fn finished(state: &GameState) -> bool {
+    matches!(state, GameState::Dead | GameState::Victory | GameState::TimeUp)
+}
+
+// Runs last in the frame: answers the step that just ended, then holds the game until the next command.
+pub fn serve_agent(
+    time: Res<Time>,
+    mut agent: ResMut<AgentEnv>,
+    observed: Observed,
+    mut state: ResMut<State<GameState>>,
+    mut config: ResMut<RunConfig>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if !agent.active() {
+        return;
+    }
+    let clock = agent.clock.get_or_insert(time.last_update().unwrap_or_else(|| time.startup()));
+    *clock += Duration::from_secs_f32(STEP);
+    *strategy = TimeUpdateStrategy::ManualInstant(*clock);
+
+    match agent.phase {
+        Phase::Stepping(left) if left > 1 && !finished(state.current()) => {
+            agent.phase = Phase::Stepping(left - 1);
+            return;
+        }
+        Phase::Stepping(_) => {
+            let reward = (observed.score.points - agent.points) as f32
+                - agent.lives.saturating_sub(observed.lives()) as f32 * LIFE_COST;
+            let reply = Reply {
+                observation: observed.observe(state.current()),
+                reward,
+                done: finished(state.current()),
+            };
+            agent.send(&reply);
+        }
+        Phase::Resetting => match state.current() {
+            GameState::Init => {
+                let _ = state.set(GameState::Game);
+                return;
+            }
+            GameState::Game => {
+                let reply = Reply { observation: observed.observe(state.current()), reward: 0., done: false };
+                agent.send(&reply);
+            }
+            _ => return,
+        },
+        Phase::Waiting => {}
+    }
+    agent.phase = Phase::Waiting;
+    agent.action = Vec2::ZERO;
+
+    // the whole game holds still until the agent says what to do
+    loop {
+        let line = match agent.read_line() {
+            Some(line) => line,
+            None => {
+                exit.send(AppExit);
+                return;
+            }
+        };
+        match serde_json::from_str::<Command>(&line) {
+            Err(e) => agent.error(e.to_string()),
+            Ok(Command::Observe) => {
+                let reply = Reply {
+                    observation: observed.observe(state.current()),
+                    reward: 0.,
+                    done: finished(state.current()),
+                };
+                agent.send(&reply);
+            }
+            Ok(Command::Close) => {
+                exit.send(AppExit);
+                return;
+            }
+            Ok(Command::Reset { seed, mode, players }) => {
+                fixed_seed.0 = seed;
+                config.mode = mode.unwrap_or(config.mode);
+                config.players = players.unwrap_or(config.players);
+                // through the title, so a new player count gets its players
+                if *state.current() == GameState::Init {
+                    let _ = state.set(GameState::Game);
+                } else {
+                    let _ = state.overwrite_set(GameState::Init);
+                }
+                agent.phase = Phase::Resetting;
+                return;
+            }
+            Ok(Command::Step { action, .. }) if action >= ACTIONS.len() => {
+                agent.error(format!("action {} is not 0 to {}", action, ACTIONS.len() - 1));
+            }
+            Ok(Command::Step { .. }) if *state.current() == GameState::Init => {
+                agent.error("no run yet, reset first".to_string());
+            }
+            Ok(Command::Step { .. }) if finished(state.current()) => {
+                let reply = Reply { observation: observed.observe(state.current()), reward: 0., done: true };
+                agent.send(&reply);
+            }
+            Ok(Command::Step { action, repeat }) => {
+                agent.action = ACTIONS[action];
+                agent.points = observed.score.points;
+                agent.lives = observed.lives();
+                agent.phase = Phase::Stepping(repeat.max(1));
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    window::WindowId,
+};
+use std::{env, process};
+
+use crate::{
+    Collider, GameState, IDmin, Orb, PickID, Pickup, Player, Score, Sizes, Speed,
+    coop::Down,
+    director::SpawnDirector,
+    menu::RestartRequest,
+    modes::{GameMode, RunConfig, RunModifiers},
+    agent::AgentEnv,
+    net::{NetRole, NetSession},
+};
+
+// idle seconds on the title before a demo run starts
+const ATTRACT_AFTER: f32 = 20.;
+// seconds the results stay up between demo runs
+const DEMO_RESULTS: f32 = 4.;
+// how far ahead the bot follows an orb's path, and in how many steps
+const LOOKAHEAD: f32 = 0.8;
+const LOOKAHEAD_STEPS: usize = 4;
+// gap to keep from an orb's edge, in player radii
+const CLEARANCE: f32 = 3.;
+// how much dodging outweighs chasing the target
+const AVOID_WEIGHT: f32 = 2.5;
+// axis parts under this share of the whole are dropped, so the bot doesn't wobble between eight directions
+const DEAD_ZONE: f32 = 0.35;
+// default soak length in seconds
+const SOAK_SECONDS: f32 = 600.;
+// more live entities than this during a soak counts as a leak
+const ENTITY_LIMIT: usize = 5_000;
+// the headless window, a common laptop size
+const HEADLESS_SIZE: (u32, u32) = (1280, 720);
+
+// The built-in bot: steers players in demos, with --autopilot and in the headless soak test.
+#[derive(Resource)]
+pub struct Autopilot {
+    // --autopilot, the bot plays every run
+    pub always: bool,
+    // a demo started from the title, any key ends it
+    pub demo: bool,
+    // --soak [seconds], back-to-back runs without a window, then exit
+    pub soak: Option<f32>,
+    // title idle time, or time on a demo's results
+    idle: f32,
+    // per seat, written by steer_bot before movement reads it
+    directions: Vec<Vec2>,
+    runs: u32,
+    survived: u32,
+    peak_entities: usize,
+}
+
+impl Autopilot {
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let soak = args.iter().position(|arg| arg == "--soak").map(|at| {
+            args.get(at + 1).and_then(|seconds| seconds.parse().ok()).unwrap_or(SOAK_SECONDS)
+        });
+        Autopilot {
+            always: args.iter().any(|arg| arg == "--autopilot"),
+            demo: false,
+            soak,
+            idle: 0.,
+            directions: Vec::new(),
+            runs: 0,
+            survived: 0,
+            peak_entities: 0,
+        }
+    }
+
+    pub fn headless(&self) -> bool {
+        self.soak.is_some()
+    }
+
+    // bot runs don't go on the boards
+    pub fn driving(&self) -> bool {
+        self.always || self.demo || self.soak.is_some()
+    }
+
+    pub fn input_for(&self, seat: usize) -> Option<Vec2> {
+        if self.driving() { self.directions.get(seat).copied() } else { None }
+    }
+}
+
+// Stands in for the window winit would have made, the game sizes everything from it.
+pub fn headless_window(mut windows: ResMut<Windows>) {
+    let (width, height) = HEADLESS_SIZE;
+    let descriptor = WindowDescriptor {
+        width: width as f32,
+        height: height as f32,
+        ..default()
+    };
+    windows.add(Window::new(WindowId::primary(), &descriptor, width, height, 1., None, None));
+}
+
+// Chases the target pickup while stepping out of the way of orbs along their path, then hunts them.
+pub fn steer_bot(
+    mut autopilot: ResMut<Autopilot>,
+    score: Res<Score>,
+    speed: Res<Speed>,
+    size: Res<Sizes>,
+    min_id: Res<IDmin>,
+    windows: Res<Windows>,
+    player_query: Query<(&Transform, &Player), Without<Down>>,
+    pick_query: Query<(&Transform, &PickID), With<Pickup>>,
+    orb_query: Query<(&Transform, &Collider), With<Orb>>,
+) {
+    if !autopilot.driving() {
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+    let half = Vec2::new(window.width(), window.height()) / 2.;
+
+    for (transform, player) in player_query.iter() {
+        let position = transform.translation.truncate();
+        let hunting = score.can_hunt(player.seat);
+        let nearest = |points: &mut dyn Iterator<Item = Vec2>| points
+            .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)));
+
+        let target = if hunting {
+            nearest(&mut orb_query.iter().map(|(orb, _)| orb.translation.truncate()))
+        } else {
+            // the guidance target, or the closest pickup when the target is the other player's
+            pick_query.iter()
+                .find(|(_, pick_id)| pick_id.number == min_id.value)
+                .map(|(pick, _)| pick.translation.truncate())
+                .or_else(|| nearest(&mut pick_query.iter().map(|(pick, _)| pick.translation.truncate())))
+        };
+        let seek = target.map_or(Vec2::ZERO, |target| (target - position).normalize_or_zero());
+
+        let mut avoid = Vec2::ZERO;
+        if !hunting {
+            let danger = CLEARANCE * size.player;
+            for (orb, collider) in orb_query.iter() {
+                for step in 0..=LOOKAHEAD_STEPS {
+                    let ahead = LOOKAHEAD * step as f32 / LOOKAHEAD_STEPS as f32;
+                    let future = orb.translation.truncate() - Vec2::new(speed.orb * ahead, 0.);
+                    let offset = position - future;
+                    let gap = offset.length() - collider.0 - size.player;
+                    if gap < danger {
+                        // nearer in time matters more
+                        let urgency = (1. - gap / danger).min(2.) * (1. - 0.5 * ahead / LOOKAHEAD);
+                        avoid += offset.normalize_or_zero() * urgency;
+                    }
+                }
+            }
+            // pinned against an edge is no way out, lean back toward the middle
+            let margin = half - 2. * size.player;
+            if position.x.abs() > margin.x {
+                avoid.x -= position.x.signum();
+            }
+            if position.y.abs() > margin.y {
+                avoid.y -= position.y.signum();
+            }
+        }
+
+        let mut direction = seek + avoid * AVOID_WEIGHT;
+        let length = direction.length();
+        if direction.x.abs() < DEAD_ZONE * length {
+            direction.x = 0.;
+        }
+        if direction.y.abs() < DEAD_ZONE * length {
+            direction.y = 0.;
+        }
+        if autopilot.directions.len() <= player.seat {
+            autopilot.directions.resize(player.seat + 1, Vec2::ZERO);
+        }
+        autopilot.directions[player.seat] = direction;
+    }
+}
+
+// Starts a demo after a while on the title and loops it until a key or button is pressed.
+pub fn attract_mode(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    net: Res<NetSession>,
+    agent: Res<AgentEnv>,
+    mut autopilot: ResMut<Autopilot>,
+    mut state: ResMut<State<GameState>>,
+    mut restart: ResMut<RestartRequest>,
+) {
+    if autopilot.always || autopilot.headless() || agent.active() {
+        return;
+    }
+    let pressed = keys.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some();
+
+    match state.current() {
+        GameState::Init => {
+            autopilot.demo = false;
+            if pressed || net.role != NetRole::Offline {
+                autopilot.idle = 0.;
+                return;
+            }
+            autopilot.idle += time.delta_seconds();
+            if autopilot.idle >= ATTRACT_AFTER {
+                autopilot.idle = 0.;
+                autopilot.demo = true;
+                let _ = state.set(GameState::Game);
+            }
+        }
+        _ if !autopilot.demo => {}
+        _ if pressed => {
+            // back to the title, whatever the key also did on this screen
+            autopilot.demo = false;
+            restart.0 = false;
+            let _ = state.overwrite_set(GameState::Init);
+        }
+        GameState::Game | GameState::Pause => autopilot.idle = 0.,
+        GameState::Dead | GameState::Victory | GameState::TimeUp => {
+            autopilot.idle += time.delta_seconds();
+            if autopilot.idle >= DEMO_RESULTS {
+                autopilot.idle = 0.;
+                restart.0 = true;
+                let _ = state.set(GameState::Game);
+            }
+        }
+    }
+}
+
+// Plays every mode and player count in turn through the title, checking the world stays sane, for --soak.
+pub fn soak_test(
+    time: Res<Time>,
+    score: Res<Score>,
+    modifiers: Res<RunModifiers>,
+    director: Res<SpawnDirector>,
+    mut autopilot: ResMut<Autopilot>,
+    mut config: ResMut<RunConfig>,
+    mut state: ResMut<State<GameState>>,
+    mut exit: EventWriter<AppExit>,
+    entity_query: Query<()>,
+    body_query: Query<&Transform, Or<(With<Player>, With<Orb>, With<Pickup>)>>,
+    pick_query: Query<(), With<Pickup>>,
+    orb_query: Query<(), With<Orb>>,
+) {
+    let limit = match autopilot.soak {
+        Some(limit) => limit,
+        None => return,
+    };
+    let entities = entity_query.iter().count();
+    autopilot.peak_entities = autopilot.peak_entities.max(entities);
+
+    let mut failures = Vec::new();
+    if entities > ENTITY_LIMIT {
+        failures.push(format!("{} entities alive", entities));
+    }
+    if *state.current() == GameState::Game {
+        if body_query.iter().any(|transform| !transform.translation.is_finite()) {
+            failures.push("a body left the number line".to_string());
+        }
+        if pick_query.iter().count() > modifiers.pickup_count {
+            failures.push(format!("{} pickups for {}", pick_query.iter().count(), modifiers.pickup_count));
+        }
+        if orb_query.iter().count() > modifiers.orb_count + director.max_orbs {
+            failures.push(format!("{} orbs alive", orb_query.iter().count()));
+        }
+    }
+    if !failures.is_empty() {
+        error!("soak failed after {} runs: {}", autopilot.runs, failures.join(", "));
+        process::exit(1);
+    }
+
+    match state.current() {
+        GameState::Init => {
+            // every mode once, then the next player count
+            if autopilot.runs > 0 {
+                config.mode = config.mode.next();
+                if config.mode == GameMode::Classic {
+                    config.players = config.players.next();
+                }
+            }
+            let _ = state.set(GameState::Game);
+        }
+        GameState::Dead | GameState::Victory | GameState::TimeUp => {
+            if state.set(GameState::Init).is_ok() {
+                autopilot.runs += 1;
+                if *state.current() != GameState::Dead {
+                    autopilot.survived += 1;
+                }
+                info!("soak run {}: {:?} {:?} ended {:?} with {} points", autopilot.runs, config.mode, config.players, state.current(), score.points);
+            }
+        }
+        GameState::Game | GameState::Pause => {}
+    }
+
+    if time.elapsed_seconds() >= limit {
+        info!("soak passed: {} runs, {} survived, peak {} entities", autopilot.runs, autopilot.survived, autopilot.peak_entities);
+        exit.send(AppExit);
+    }
+}
@@ -0,0 +1,289 @@
+// Heatmaps of deaths, pickups and where players spent their time, from the game's telemetry.jsonl.
+//
+//     cargo run --bin heatmap -- [telemetry.jsonl] [--out heatmaps] [--mode Classic] [--players Solo] [--all-drivers]
+//
+// Writes deaths.svg, pickups.svg and occupancy.svg over the playfield, normalized so runs
+// at any window size line up. Only runs people played count unless --all-drivers lets in
+// the bot, agents and network clients too.
+
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+// cells across and down, the 16:9 of most screens
+const COLUMNS: usize = 64;
+const ROWS: usize = 36;
+// pixels per cell in the output
+const CELL: f32 = 15.;
+// room above the field for the title and below for the legend
+const HEADER: f32 = 40.;
+const FOOTER: f32 = 40.;
+// cold to hot, the low end just off the background
+const RAMP: [(f32, [f32; 3]); 4] = [
+    (0., [27., 31., 59.]),
+    (0.33, [91., 42., 134.]),
+    (0.66, [228., 87., 46.]),
+    (1., [255., 209., 102.]),
+];
+
+struct Options {
+    log: PathBuf,
+    out: PathBuf,
+    mode: Option<String>,
+    players: Option<String>,
+    all_drivers: bool,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let mut options = Options {
+            log: PathBuf::from("telemetry.jsonl"),
+            out: PathBuf::from("heatmaps"),
+            mode: None,
+            players: None,
+            all_drivers: false,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().unwrap_or_else(|| fail(&format!("{} needs a value", name)));
+            match arg.as_str() {
+                "--out" => options.out = PathBuf::from(value("--out")),
+                "--mode" => options.mode = Some(value("--mode")),
+                "--players" => options.players = Some(value("--players")),
+                "--all-drivers" => options.all_drivers = true,
+                "-h" | "--help" => {
+                    println!("usage: heatmap [telemetry.jsonl] [--out dir] [--mode Classic|TimeAttack|Endless|Daily] [--players Solo|Coop|Versus] [--all-drivers]");
+                    process::exit(0);
+                }
+                _ if arg.starts_with("--") => fail(&format!("unknown option {}", arg)),
+                _ => options.log = PathBuf::from(arg),
+            }
+        }
+        options
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("heatmap: {}", message);
+    process::exit(1);
+}
+
+// Counts per cell, plus the exact spots for maps sparse enough to show them.
+struct Heatmap {
+    title: &'static str,
+    cells: Vec<u32>,
+    spots: Vec<(f32, f32)>,
+    // exact spots are drawn over the cells
+    marks: bool,
+}
+
+impl Heatmap {
+    fn new(title: &'static str, marks: bool) -> Self {
+        Heatmap {
+            title,
+            cells: vec![0; COLUMNS * ROWS],
+            spots: Vec::new(),
+            marks,
+        }
+    }
+
+    // u and v run 0 to 1 from the top left
+    fn add(&mut self, u: f32, v: f32) {
+        let column = ((u * COLUMNS as f32) as usize).min(COLUMNS - 1);
+        let row = ((v * ROWS as f32) as usize).min(ROWS - 1);
+        self.cells[row * COLUMNS + column] += 1;
+        if self.marks {
+            self.spots.push((u, v));
+        }
+    }
+
+    fn total(&self) -> u32 {
+        self.cells.iter().sum()
+    }
+
+    fn svg(&self, subtitle: &str) -> String {
+        let width = COLUMNS as f32 * CELL;
+        let height = ROWS as f32 * CELL;
+        let max = self.cells.iter().copied().max().unwrap_or(0).max(1) as f32;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n\
+             <rect width=\"{w}\" height=\"{h}\" fill=\"#0b0d1a\"/>\n\
+             <text x=\"10\" y=\"26\" fill=\"#ffffff\" font-size=\"18\">{title}</text>\n\
+             <text x=\"{right}\" y=\"26\" fill=\"#9aa0b8\" font-size=\"13\" text-anchor=\"end\">{subtitle}</text>\n\
+             <g transform=\"translate(0 {HEADER})\">\n\
+             <rect width=\"{width}\" height=\"{height}\" fill=\"none\" stroke=\"#3a3f5c\"/>\n",
+            w = width,
+            h = height + HEADER + FOOTER,
+            title = self.title,
+            right = width - 10.,
+            subtitle = subtitle,
+        );
+        for (index, &count) in self.cells.iter().enumerate() {
+            if count == 0 {
+                continue;
+            }
+            // square root so a few hot cells don't wash out the rest
+            let heat = (count as f32 / max).sqrt();
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"{}\"><title>{}</title></rect>\n",
+                (index % COLUMNS) as f32 * CELL,
+                (index / COLUMNS) as f32 * CELL,
+                color(heat),
+                count,
+            );
+        }
+        for (u, v) in &self.spots {
+            svg += &format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2.5\" fill=\"#ffffff\" fill-opacity=\"0.7\"/>\n",
+                u * width,
+                v * height,
+            );
+        }
+        svg += "</g>\n";
+
+        // legend, zero to the busiest cell
+        let legend_y = HEADER + height + 12.;
+        let steps = 20;
+        for step in 0..steps {
+            let heat = step as f32 / (steps - 1) as f32;
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{legend_y}\" width=\"12\" height=\"12\" fill=\"{}\"/>\n",
+                10. + step as f32 * 12.,
+                color(heat),
+            );
+        }
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" fill=\"#9aa0b8\" font-size=\"12\">1 to {} per cell, {} in all</text>\n</svg>\n",
+            20. + steps as f32 * 12.,
+            legend_y + 11.,
+            max as u32,
+            self.total(),
+        );
+        svg
+    }
+}
+
+// World position to 0 to 1 from the top left, the game's origin is the middle of the screen with y up.
+fn normalize(x: f32, y: f32, width: f32, height: f32) -> (f32, f32) {
+    (x / width + 0.5, 0.5 - y / height)
+}
+
+fn color(heat: f32) -> String {
+    let heat = heat.clamp(0., 1.);
+    let upper = RAMP.iter().position(|(stop, _)| *stop >= heat).unwrap_or(RAMP.len() - 1).max(1);
+    let (low, from) = RAMP[upper - 1];
+    let (high, to) = RAMP[upper];
+    let t = (heat - low) / (high - low);
+    let channel = |i: usize| (from[i] + (to[i] - from[i]) * t).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(0), channel(1), channel(2))
+}
+
+fn main() {
+    let options = Options::from_args();
+    let text = fs::read_to_string(&options.log)
+        .unwrap_or_else(|e| fail(&format!("could not read {}: {}", options.log.display(), e)));
+
+    let mut deaths = Heatmap::new("Deaths", true);
+    let mut pickups = Heatmap::new("Pickups collected", false);
+    let mut occupancy = Heatmap::new("Player occupancy", false);
+    // (session, run) to the screen it was played on, None when filtered out
+    let mut runs: HashMap<(u64, u64), Option<(f32, f32)>> = HashMap::new();
+    let mut skipped = 0;
+    let mut driven = 0;
+
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let event: Value = match serde_json::from_str(line) {
+            Ok(event) => event,
+            Err(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let number = |key: &str| event.get(key).and_then(Value::as_f64);
+        let run = (number("session").unwrap_or(0.) as u64, number("run").unwrap_or(0.) as u64);
+
+        if event["event"] == "run_start" {
+            let config = &event["config"];
+            let wanted = |filter: &Option<String>, key: &str| filter.iter().all(|name| config[key] == name.as_str());
+            let screen = match (number("width"), number("height")) {
+                (Some(width), Some(height)) if width > 0. && height > 0. => Some((width as f32, height as f32)),
+                _ => None,
+            };
+            // logs from before drivers were written down are taken as played by people
+            let human = event.get("driver").iter().all(|driver| *driver == "human");
+            if !human && !options.all_drivers {
+                driven += 1;
+            }
+            let kept = (human || options.all_drivers) && wanted(&options.mode, "mode") && wanted(&options.players, "players");
+            runs.insert(run, screen.filter(|_| kept));
+            continue;
+        }
+        // a run whose start was cut off the log has no screen to normalize by
+        let Some(&Some((width, height))) = runs.get(&run) else { continue };
+        let (Some(x), Some(y)) = (number("x"), number("y")) else { continue };
+        let (u, v) = normalize(x as f32, y as f32, width, height);
+
+        match event["event"].as_str() {
+            Some("death") => deaths.add(u, v),
+            Some("pickup") => pickups.add(u, v),
+            Some("position") => occupancy.add(u, v),
+            _ => {}
+        }
+    }
+    if skipped > 0 {
+        eprintln!("heatmap: skipped {} lines that weren't JSON", skipped);
+    }
+    if driven > 0 {
+        eprintln!("heatmap: left out {} runs played by the bot, an agent or a client, --all-drivers keeps them", driven);
+    }
+
+    let kept = runs.values().filter(|screen| screen.is_some()).count();
+    let mut filters = vec![format!("{} runs", kept)];
+    filters.extend(options.mode.iter().cloned());
+    filters.extend(options.players.iter().cloned());
+    if options.all_drivers {
+        filters.push("all drivers".to_string());
+    }
+    let subtitle = filters.join(", ");
+
+    fs::create_dir_all(&options.out)
+        .unwrap_or_else(|e| fail(&format!("could not create {}: {}", options.out.display(), e)));
+    for (name, map) in [("deaths", &deaths), ("pickups", &pickups), ("occupancy", &occupancy)] {
+        let path: PathBuf = Path::new(&options.out).join(format!("{}.svg", name));
+        fs::write(&path, map.svg(&subtitle))
+            .unwrap_or_else(|e| fail(&format!("could not write {}: {}", path.display(), e)));
+        println!("{}: {} from {}", path.display(), map.total(), subtitle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: f32, y: f32) -> usize {
+        let mut map = Heatmap::new("test", false);
+        let (u, v) = normalize(x, y, 1280., 720.);
+        map.add(u, v);
+        map.cells.iter().position(|&count| count == 1).unwrap()
+    }
+
+    #[test]
+    fn corners_land_in_corner_cells() {
+        assert_eq!(cell(-640., 360.), 0);
+        assert_eq!(cell(639., 360.), COLUMNS - 1);
+        assert_eq!(cell(-640., -359.), (ROWS - 1) * COLUMNS);
+        // the far edges are on the field too
+        assert_eq!(cell(640., -360.), ROWS * COLUMNS - 1);
+    }
+
+    #[test]
+    fn middle_lands_in_middle_cell() {
+        assert_eq!(cell(0., 0.), ROWS / 2 * COLUMNS + COLUMNS / 2);
+        // just up and left of the middle
+        assert_eq!(cell(-1., 1.), (ROWS / 2 - 1) * COLUMNS + COLUMNS / 2 - 1);
+    }
+}
@@ -0,0 +1,144 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+use rand::Rng;
+use std::f32::consts::PI;
+
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.5;
+// strongest shake, as a fraction of the window width and in radians
+const MAX_SHAKE_OFFSET: f32 = 0.02;
+const MAX_SHAKE_ANGLE: f32 = 0.04;
+const ZOOM_DURATION: f32 = 0.6;
+const ZOOM_AMOUNT: f32 = 0.12;
+// flash alpha lost per second
+const FLASH_FADE: f32 = 2.5;
+// with reduce motion on, flashes are capped to this alpha
+const CALM_FLASH: f32 = 0.25;
+
+#[derive(Component)]
+pub struct MainCamera;
+#[derive(Component)]
+pub struct ScreenFlash;
+
+pub enum CameraEvent {
+    // adds trauma, 0..1
+    Shake(f32),
+    ZoomPulse,
+    Flash(Color),
+}
+
+#[derive(Resource)]
+pub struct CameraSettings {
+    pub shake: f32,
+    pub zoom: f32,
+    pub flash: f32,
+    // turns shake and zoom off and tones flashes down
+    pub reduce_motion: bool,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            shake: 1.,
+            zoom: 1.,
+            flash: 1.,
+            reduce_motion: false,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraFx {
+    trauma: f32,
+    zoom_time: f32,
+    flash: Color,
+}
+
+impl Default for CameraFx {
+    fn default() -> Self {
+        CameraFx {
+            trauma: 0.,
+            zoom_time: 0.,
+            flash: Color::NONE,
+        }
+    }
+}
+
+pub fn spawn_camera(commands: &mut Commands) {
+    commands.spawn(Camera2dBundle::default()).insert(MainCamera);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            focus_policy: FocusPolicy::Pass,
+            z_index: ZIndex::Global(100),
+            ..default()
+        })
+        .insert(ScreenFlash);
+}
+
+pub fn camera_events(
+    settings: Res<CameraSettings>,
+    mut fx: ResMut<CameraFx>,
+    mut events: EventReader<CameraEvent>,
+) {
+    for event in events.iter() {
+        match event {
+            CameraEvent::Shake(amount) => {
+                if !settings.reduce_motion {
+                    fx.trauma = (fx.trauma + amount * settings.shake).min(1.);
+                }
+            }
+            CameraEvent::ZoomPulse => {
+                if !settings.reduce_motion {
+                    fx.zoom_time = ZOOM_DURATION;
+                }
+            }
+            CameraEvent::Flash(color) => {
+                let mut alpha = color.a() * settings.flash;
+                if settings.reduce_motion {
+                    alpha = alpha.min(CALM_FLASH);
+                }
+                fx.flash = *color;
+                fx.flash.set_a(alpha);
+            }
+        }
+    }
+}
+
+pub fn camera_effects(
+    time: Res<Time>,
+    settings: Res<CameraSettings>,
+    windows: Res<Windows>,
+    mut fx: ResMut<CameraFx>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut flash_query: Query<&mut BackgroundColor, With<ScreenFlash>>,
+) {
+    let window = windows.get_primary().unwrap();
+    let dt = time.delta_seconds();
+    let mut rng = rand::thread_rng();
+
+    fx.trauma = (fx.trauma - TRAUMA_DECAY * dt).max(0.);
+    fx.zoom_time = (fx.zoom_time - dt).max(0.);
+    let flash_alpha = (fx.flash.a() - FLASH_FADE * dt).max(0.);
+    fx.flash.set_a(flash_alpha);
+
+    // squared so small hits stay subtle and big ones really kick
+    let shake = fx.trauma * fx.trauma;
+    let zoom = ZOOM_AMOUNT * settings.zoom * (PI * fx.zoom_time / ZOOM_DURATION).sin();
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        transform.translation.x = MAX_SHAKE_OFFSET * window.width() * shake * rng.gen_range(-1. ..1.);
+        transform.translation.y = MAX_SHAKE_OFFSET * window.width() * shake * rng.gen_range(-1. ..1.);
+        transform.rotation = Quat::from_rotation_z(MAX_SHAKE_ANGLE * shake * rng.gen_range(-1. ..1.));
+        projection.scale = 1. - zoom;
+    }
+
+    for mut background in flash_query.iter_mut() {
+        background.0 = fx.flash;
+    }
+}
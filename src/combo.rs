@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+
+use crate::Player;
+
+// How a streak of pickups builds and how long it holds.
+#[derive(Clone, Copy)]
+pub struct ComboRules {
+    // seconds after a pickup in which the next one keeps the chain going
+    pub window: f32,
+    // pickups in a row per multiplier step
+    pub step: u32,
+    pub max_multiplier: u32,
+}
+
+// A streak in progress: pickups in a row and the time left to extend it.
+#[derive(Clone, Copy, Default)]
+pub struct Chain {
+    length: u32,
+    timer: f32,
+}
+
+impl Chain {
+    pub fn multiplier(&self, rules: &ComboRules) -> u32 {
+        (1 + self.length / rules.step).min(rules.max_multiplier)
+    }
+
+    // counts a pickup, returns the points it is worth
+    fn pickup(&mut self, rules: &ComboRules) -> i32 {
+        let points = self.multiplier(rules) as i32;
+        self.length += 1;
+        self.timer = rules.window;
+        points
+    }
+
+    pub fn reset(&mut self) {
+        self.length = 0;
+        self.timer = 0.;
+    }
+
+    // share of the window left, for the HUD
+    pub fn remaining(&self, rules: &ComboRules) -> f32 {
+        (self.timer / rules.window).clamp(0., 1.)
+    }
+
+    // A lapsed window costs one multiplier step and gives a fresh window to win it back.
+    fn decay(&mut self, rules: &ComboRules, delta: f32) {
+        if self.length == 0 {
+            return;
+        }
+        self.timer -= delta;
+        if self.timer <= 0. {
+            let level = self.length / rules.step;
+            if level == 0 {
+                self.reset();
+            } else {
+                self.length = (level - 1) * rules.step;
+                self.timer = rules.window;
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Combo {
+    pub rules: ComboRules,
+    // solo and co-op build one streak together, versus rivals keep their own on Player
+    pub shared: Chain,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Combo {
+            rules: ComboRules {
+                window: 1.5,
+                step: 5,
+                max_multiplier: 5,
+            },
+            shared: Chain::default(),
+        }
+    }
+}
+
+impl Combo {
+    // the streak a player's pickups and hits count against
+    pub fn chain<'a>(&'a mut self, own: &'a mut Chain, race: bool) -> &'a mut Chain {
+        if race { own } else { &mut self.shared }
+    }
+
+    // counts a pickup on the right streak, returns the points it is worth
+    pub fn pickup(&mut self, own: &mut Chain, race: bool) -> i32 {
+        let rules = self.rules;
+        self.chain(own, race).pickup(&rules)
+    }
+}
+
+pub fn decay_combo(
+    time: Res<Time>,
+    mut combo: ResMut<Combo>,
+    mut player_query: Query<&mut Player>,
+) {
+    let rules = combo.rules;
+    combo.shared.decay(&rules, time.delta_seconds());
+    for mut player in player_query.iter_mut() {
+        player.chain.decay(&rules, time.delta_seconds());
+    }
+}
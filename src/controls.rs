@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+// Every key the game listens to, so prompts can name the real binding.
+#[derive(Resource)]
+pub struct Controls {
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    // the second player in co-op
+    pub p2_up: KeyCode,
+    pub p2_down: KeyCode,
+    pub p2_left: KeyCode,
+    pub p2_right: KeyCode,
+    pub confirm: KeyCode,
+    pub mute: KeyCode,
+    pub theme: KeyCode,
+    pub color_vision: KeyCode,
+    pub high_contrast: KeyCode,
+    pub shape_cues: KeyCode,
+    pub language: KeyCode,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            up: KeyCode::W,
+            down: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+            p2_up: KeyCode::Up,
+            p2_down: KeyCode::Down,
+            p2_left: KeyCode::Left,
+            p2_right: KeyCode::Right,
+            confirm: KeyCode::Space,
+            mute: KeyCode::M,
+            theme: KeyCode::T,
+            color_vision: KeyCode::C,
+            high_contrast: KeyCode::H,
+            shape_cues: KeyCode::G,
+            language: KeyCode::L,
+        }
+    }
+}
+
+impl Controls {
+    // placeholder name used in string tables, e.g. "{confirm}"
+    pub fn named(&self) -> [(&'static str, KeyCode); 15] {
+        [
+            ("up", self.up),
+            ("down", self.down),
+            ("left", self.left),
+            ("right", self.right),
+            ("p2_up", self.p2_up),
+            ("p2_down", self.p2_down),
+            ("p2_left", self.p2_left),
+            ("p2_right", self.p2_right),
+            ("confirm", self.confirm),
+            ("mute", self.mute),
+            ("theme", self.theme),
+            ("color_vision", self.color_vision),
+            ("high_contrast", self.high_contrast),
+            ("shape_cues", self.shape_cues),
+            ("language", self.language),
+        ]
+    }
+
+    // up, down, left, right for a player seat
+    pub fn seat_keys(&self, seat: usize) -> [KeyCode; 4] {
+        if seat == 0 {
+            [self.up, self.down, self.left, self.right]
+        } else {
+            [self.p2_up, self.p2_down, self.p2_left, self.p2_right]
+        }
+    }
+
+    // where a seat is being steered, -1, 0 or 1 on each axis; each seat also takes the gamepad in the same place
+    pub fn seat_direction(&self, seat: usize, input: &Input<KeyCode>, gamepads: &Gamepads, buttons: &Input<GamepadButton>) -> Vec2 {
+        let [up, down, left, right] = self.seat_keys(seat);
+        let gamepad = gamepads.iter().nth(seat);
+        let pressed = |key, button| input.pressed(key)
+            || matches!(gamepad, Some(gamepad) if buttons.pressed(GamepadButton::new(gamepad, button)));
+
+        let mut direction = Vec2::ZERO;
+        if pressed(up, GamepadButtonType::DPadUp) {
+            direction.y += 1.;
+        }
+        if pressed(down, GamepadButtonType::DPadDown) {
+            direction.y -= 1.;
+        }
+        if pressed(right, GamepadButtonType::DPadRight) {
+            direction.x += 1.;
+        }
+        if pressed(left, GamepadButtonType::DPadLeft) {
+            direction.x -= 1.;
+        }
+        direction
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use crate::{
+    Effects, GameState, Orb, Player, Sizes, Speed,
+    camera::CameraEvent,
+    combo::Combo,
+    controls::Controls,
+    hud::LivesText,
+    i18n::Locale,
+    modes::{Players, RunConfig},
+    particles::{Emitter, ParticleBurst},
+    telemetry::TelemetryEvent,
+    theme::{Paint, Theme},
+};
+
+// seconds a player can't be hit after losing a life
+const RECOVERY: f32 = 2.;
+// blinks per second while recovering
+const BLINK_RATE: f32 = 8.;
+
+// Out of lives, waiting for the others to finish the run. Skipped by movement and collisions.
+#[derive(Component)]
+pub struct Down;
+
+// A player touched a dangerous orb, sent by manage_collisions with the player then the orb.
+pub struct PlayerHit(pub Entity, pub Entity);
+
+// Each seat has its own core color so two players tell themselves apart.
+pub fn core_paint(seat: usize) -> Paint {
+    if seat == 0 { Paint::PlayerCore } else { Paint::PartnerCore }
+}
+
+// Where a seat starts a run, co-op players side by side around the solo spot.
+pub fn start_position(seat: usize, players: Players, x: f32, window: &Window) -> Vec3 {
+    let y = match players {
+        Players::Solo => 0.,
+        Players::Coop | Players::Versus => if seat == 0 { window.height()/8. } else { -window.height()/8. },
+    };
+    Vec3::new(x, y, 2.)
+}
+
+pub fn take_hits(
+    mut commands: Commands,
+    mut hits: EventReader<PlayerHit>,
+    mut effects: Effects,
+    mut combo: ResMut<Combo>,
+    mut state: ResMut<State<GameState>>,
+    config: Res<RunConfig>,
+    theme: Res<Theme>,
+    size: Res<Sizes>,
+    speed: Res<Speed>,
+    mut player_query: Query<(Entity, &mut Player, &Transform, &mut Visibility), Without<Down>>,
+    orb_query: Query<&Transform, With<Orb>>,
+) {
+    let mut out = Vec::new();
+    for PlayerHit(entity, orb) in hits.iter() {
+        if let Ok((_, mut player, transform, _)) = player_query.get_mut(*entity) {
+            if player.recovering > 0. || player.lives == 0 {
+                continue;
+            }
+            effects.particles.send(ParticleBurst {
+                position: transform.translation,
+                direction: Vec2::X,
+                scale: size.player,
+                emitter: Emitter::explosion(theme.explosion),
+            });
+            effects.camera.send(CameraEvent::Shake(1.));
+            effects.camera.send(CameraEvent::Flash(theme.death_flash));
+            // a hit breaks the streak it was building, only the hit rival's in versus
+            combo.chain(&mut player.chain, config.players == Players::Versus).reset();
+
+            player.lives -= 1;
+            let orb_at = orb_query.get(*orb).map_or(Vec3::ZERO, |orb| orb.translation);
+            effects.telemetry.send(TelemetryEvent::Death {
+                seat: player.seat,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                orb: orb.index(),
+                orb_x: orb_at.x,
+                orb_y: orb_at.y,
+                orb_speed: speed.orb,
+                lives: player.lives,
+            });
+            if player.lives > 0 {
+                player.recovering = RECOVERY;
+            } else {
+                out.push(*entity);
+            }
+        }
+    }
+    if out.is_empty() {
+        return;
+    }
+
+    let standing = player_query.iter().filter(|(_, player, _, _)| player.lives > 0).count();
+    // a versus run is over once one player is left, decide_winner names them
+    let run_over = standing == 0 || (config.players == Players::Versus && standing == 1);
+    for entity in out {
+        commands.entity(entity).insert(Down);
+        // the last one down stays on screen under the results
+        if !run_over {
+            if let Ok((_, _, _, mut visibility)) = player_query.get_mut(entity) {
+                visibility.is_visible = false;
+            }
+        }
+    }
+    if run_over {
+        // another end may already be queued this frame
+        let _ = state.set(GameState::Dead);
+    }
+}
+
+pub fn recover(
+    time: Res<Time>,
+    mut player_query: Query<(&mut Player, &mut Visibility), Without<Down>>,
+) {
+    for (mut player, mut visibility) in player_query.iter_mut() {
+        if player.recovering <= 0. {
+            continue;
+        }
+        player.recovering = (player.recovering - time.delta_seconds()).max(0.);
+        visibility.is_visible = player.recovering <= 0. || (player.recovering * BLINK_RATE) as i32 % 2 == 0;
+    }
+}
+
+// Lives per player, only with two players where there is more than the one life.
+pub fn update_lives(
+    config: Res<RunConfig>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    player_query: Query<&Player>,
+    mut text_query: Query<&mut Text, With<LivesText>>,
+) {
+    let key = match config.players {
+        Players::Solo => "",
+        Players::Coop => "lives",
+        // rivals keep their own points too
+        Players::Versus => "versus_status",
+    };
+    let lives = if key.is_empty() {
+        String::new()
+    } else {
+        let mut players: Vec<_> = player_query.iter().collect();
+        players.sort_by_key(|player| player.seat);
+        players.iter()
+            .map(|player| locale.format(key, &controls, &[
+                ("player", (player.seat + 1).to_string()),
+                ("lives", player.lives.to_string()),
+                ("points", player.points.to_string()),
+            ]))
+            .collect::<Vec<_>>()
+            .join("    ")
+    };
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != lives {
+            text.sections[0].value = lives.clone();
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    GameState, GameTime, Score,
+    controls::Controls,
+    ghost::{GhostLibrary, GhostRecorder},
+    i18n::Locale,
+    modes::{GameMode, OrbBehavior, Players, RunConfig, RunModifiers},
+    storage::{load_ron, save_ron},
+};
+
+// written next to scores.ron
+const HISTORY_PATH: &str = "daily.ron";
+// entries shown on the high scores page
+const HISTORY_SHOWN: usize = 10;
+
+// Days since 1970-01-01 in UTC, so the whole team rolls over to the next challenge together.
+fn today() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() as i64 / 86_400)
+}
+
+// YYYY-MM-DD for a day count, after Howard Hinnant's civil_from_days.
+fn date_string(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// splitmix64, spreads neighbouring days over the whole seed range
+fn seed_for(days: i64) -> u64 {
+    let mut z = (days as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+fn modifiers_for(seed: u64) -> RunModifiers {
+    // its own stream, so the modifiers don't shift the layout the seed gives
+    let mut rng = ChaCha8Rng::seed_from_u64(!seed);
+    RunModifiers {
+        orb_count: rng.gen_range(3..=6),
+        pickup_count: rng.gen_range(6..=14),
+        orb_behavior: match rng.gen_range(0..3) {
+            0 => OrbBehavior::Straight,
+            1 => OrbBehavior::Wave,
+            _ => OrbBehavior::Homing,
+        },
+        // quarter steps between half and double the usual speed-up
+        speed_ramp: rng.gen_range(2..=8) as f32 * 0.25,
+    }
+}
+
+#[derive(Resource)]
+pub struct DailyChallenge {
+    day: i64,
+    pub date: String,
+    pub seed: u64,
+    pub modifiers: RunModifiers,
+    // whether the current run claimed the day and goes into the history
+    pub official: bool,
+}
+
+impl DailyChallenge {
+    fn for_day(day: i64) -> Self {
+        let seed = seed_for(day);
+        DailyChallenge {
+            day,
+            date: date_string(day),
+            seed,
+            modifiers: modifiers_for(seed),
+            official: false,
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let day = today();
+        if day != self.day {
+            *self = DailyChallenge::for_day(day);
+        }
+    }
+
+    // Claims the day for a run that's starting, as a loss until it ends otherwise. Claimed up front,
+    // so however the run is left, restarted or quit to the title, later runs that day are practice.
+    pub fn claim(&mut self, history: &mut DailyHistory, players: Players) {
+        // co-op has extra lives, only solo runs count for the day
+        self.official = players == Players::Solo && history.attempt(&self.date).is_none();
+        if self.official {
+            history.attempts.push(DailyAttempt {
+                date: self.date.clone(),
+                won: false,
+                time: 0.,
+                points: 0,
+            });
+            history.save();
+        }
+    }
+
+    // the title screen line for the current challenge
+    pub fn describe(&self, history: &DailyHistory, locale: &Locale, controls: &Controls) -> String {
+        let behavior = match self.modifiers.orb_behavior {
+            OrbBehavior::Straight => "behavior_straight",
+            OrbBehavior::Wave => "behavior_wave",
+            OrbBehavior::Homing => "behavior_homing",
+        };
+        let mut lines = vec![locale.format("daily_today", controls, &[
+            ("date", self.date.clone()),
+            ("orbs", self.modifiers.orb_count.to_string()),
+            ("pickups", self.modifiers.pickup_count.to_string()),
+            ("behavior", locale.text(behavior, controls)),
+            ("ramp", format!("{:.2}", self.modifiers.speed_ramp)),
+        ])];
+        if history.attempt(&self.date).is_some() {
+            lines.push(locale.text("daily_done", controls));
+        }
+        lines.join("\n")
+    }
+}
+
+impl Default for DailyChallenge {
+    fn default() -> Self {
+        DailyChallenge::for_day(today())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DailyAttempt {
+    pub date: String,
+    pub won: bool,
+    pub time: f32,
+    pub points: i32,
+}
+
+// The first run started each day, later ones that day are practice.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct DailyHistory {
+    attempts: Vec<DailyAttempt>,
+}
+
+impl DailyHistory {
+    pub fn load() -> Self {
+        load_ron(HISTORY_PATH)
+    }
+
+    pub fn save(&self) {
+        save_ron(HISTORY_PATH, self);
+    }
+
+    pub fn attempt(&self, date: &str) -> Option<&DailyAttempt> {
+        self.attempts.iter().find(|attempt| attempt.date == date)
+    }
+
+    // newest first, for the high scores page
+    pub fn lines(&self, locale: &Locale, controls: &Controls) -> Vec<String> {
+        if self.attempts.is_empty() {
+            return vec![locale.text("scores_empty", controls)];
+        }
+        self.attempts.iter().rev().take(HISTORY_SHOWN)
+            .map(|attempt| locale.format(if attempt.won { "daily_won" } else { "daily_lost" }, controls, &[
+                ("date", attempt.date.clone()),
+                ("time", format!("{:.1}", attempt.time)),
+                ("points", attempt.points.to_string()),
+            ]))
+            .collect()
+    }
+}
+
+// Runs on the defeat and victory screens, filling in the attempt prepare_run claimed.
+// A won attempt is the day's ghost, for the practice runs after it.
+pub fn record_daily(
+    state: Res<State<GameState>>,
+    config: Res<RunConfig>,
+    time: Res<GameTime>,
+    score: Res<Score>,
+    daily: Res<DailyChallenge>,
+    recorder: Res<GhostRecorder>,
+    mut history: ResMut<DailyHistory>,
+    mut ghosts: ResMut<GhostLibrary>,
+) {
+    if config.mode != GameMode::Daily || !daily.official {
+        return;
+    }
+    if let Some(attempt) = history.attempts.iter_mut().rev().find(|attempt| attempt.date == daily.date) {
+        attempt.won = *state.current() == GameState::Victory;
+        attempt.time = time.value;
+        attempt.points = score.points;
+        history.save();
+    }
+    if *state.current() == GameState::Victory {
+        ghosts.store(&config, &daily, recorder.run.clone());
+        ghosts.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_string_known_days() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(-1), "1969-12-31");
+        assert_eq!(date_string(19_722), "2023-12-31");
+    }
+
+    #[test]
+    fn date_string_leap_years() {
+        // every fourth year, not every hundredth, but every four hundredth
+        assert_eq!(date_string(11_016), "2000-02-29");
+        assert_eq!(date_string(11_017), "2000-03-01");
+        assert_eq!(date_string(19_782), "2024-02-29");
+        assert_eq!(date_string(47_540), "2100-02-28");
+        assert_eq!(date_string(47_541), "2100-03-01");
+        assert_eq!(date_string(-25_508), "1900-03-01");
+    }
+
+    #[test]
+    fn seed_for_is_stable() {
+        // everyone's game has to agree on these, whatever the build
+        assert_eq!(seed_for(0), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(seed_for(1), 0x910A_2DEC_8902_5CC1);
+        assert_eq!(seed_for(20_000), 0xE7AA_7324_AE5D_121E);
+    }
+
+    #[test]
+    fn modifiers_for_is_stable() {
+        let modifiers = modifiers_for(seed_for(20_000));
+        assert_eq!(modifiers.orb_count, 6);
+        assert_eq!(modifiers.pickup_count, 6);
+        assert_eq!(modifiers.orb_behavior, OrbBehavior::Wave);
+        assert_eq!(modifiers.speed_ramp, 1.75);
+
+        let modifiers = modifiers_for(seed_for(0));
+        assert_eq!(modifiers.orb_count, 3);
+        assert_eq!(modifiers.pickup_count, 9);
+        assert_eq!(modifiers.orb_behavior, OrbBehavior::Homing);
+        assert_eq!(modifiers.speed_ramp, 2.);
+    }
+}
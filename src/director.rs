@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameTime, Orb, Player, Score, Sizes, Speed, spawn_orb,
+    coop::Down,
+    modes::{GameMode, GameRng, RunConfig, Stream},
+    placement::{Blocker, find_spot, screen_bounds},
+    theme::Theme,
+};
+
+// Endless mode: brings in extra orbs on a shrinking interval and keeps speeding all of them up.
+#[derive(Resource)]
+pub struct SpawnDirector {
+    // seconds between new orbs at the start of a run
+    pub interval: f32,
+    pub min_interval: f32,
+    // seconds of play for the interval to halve
+    pub halving: f32,
+    pub max_orbs: usize,
+    // orb speed gained per second of play, in screen widths per second
+    pub acceleration: f32,
+    timer: f32,
+    // whole seconds already paid out as points
+    seconds_scored: i32,
+}
+
+impl Default for SpawnDirector {
+    fn default() -> Self {
+        SpawnDirector {
+            interval: 12.,
+            min_interval: 3.,
+            halving: 60.,
+            max_orbs: 14,
+            acceleration: 0.002,
+            timer: 12.,
+            seconds_scored: 0,
+        }
+    }
+}
+
+impl SpawnDirector {
+    pub fn reset(&mut self) {
+        self.timer = self.interval;
+        self.seconds_scored = 0;
+    }
+
+    fn current_interval(&self, time: f32) -> f32 {
+        (self.interval * 0.5_f32.powf(time / self.halving)).max(self.min_interval)
+    }
+}
+
+// Orbs the director added, on top of the 4 every run starts with.
+#[derive(Component)]
+pub struct Reinforcement;
+
+pub fn direct_spawns(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut director: ResMut<SpawnDirector>,
+    mut speed: ResMut<Speed>,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
+    config: Res<RunConfig>,
+    game_time: Res<GameTime>,
+    time: Res<Time>,
+    size: Res<Sizes>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    orb_query: Query<&Transform, With<Orb>>,
+    mut player_query: Query<&mut Player, Without<Down>>,
+) {
+    if config.mode != GameMode::Endless {
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+
+    // the score is survival time plus whatever the pickups paid
+    let survived = game_time.value as i32;
+    score.points += survived - director.seconds_scored;
+    // in versus only the players still in it earn the seconds
+    for mut player in player_query.iter_mut() {
+        player.points += survived - director.seconds_scored;
+    }
+    director.seconds_scored = survived;
+
+    speed.orb += director.acceleration * window.width() * time.delta_seconds();
+
+    director.timer -= time.delta_seconds();
+    if director.timer > 0. {
+        return;
+    }
+    director.timer = director.current_interval(game_time.value);
+    if orb_query.iter().count() >= director.max_orbs {
+        return;
+    }
+
+    // come in from the right edge like a recycled orb, clear of the ones already there
+    let blockers: Vec<_> = orb_query.iter()
+        .map(|transform| Blocker::new(transform.translation, size.orb))
+        .collect();
+    let x = window.width()/2. + size.orb;
+    let (min, max) = screen_bounds(window, size.orb);
+    let spot = find_spot(&mut rng.stream(Stream::Reinforcement, 0), Vec2::new(x, min.y), Vec2::new(x, max.y), size.orb, &blockers);
+    let orb = spawn_orb(&mut commands, &mut meshes, &mut materials, &theme, size.orb, spot.extend(1.));
+    commands.entity(orb).insert(Reinforcement);
+}
+
+// A new run starts from the 4 standard orbs again.
+pub fn reset_director(
+    mut commands: Commands,
+    mut director: ResMut<SpawnDirector>,
+    reinforcement_query: Query<Entity, With<Reinforcement>>,
+) {
+    director.reset();
+    for orb in reinforcement_query.iter() {
+        commands.entity(orb).despawn_recursive();
+    }
+}
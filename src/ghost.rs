@@ -0,0 +1,237 @@
+use bevy::{
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    GameTime, Player, Sizes,
+    controls::Controls,
+    daily::DailyChallenge,
+    hud::GhostDeltaText,
+    i18n::Locale,
+    modes::{GameMode, RunConfig},
+    scores::board_key,
+    stats::RunStats,
+    storage::{load_ron, save_ron},
+    theme::Theme,
+};
+
+// written next to scores.ron
+const GHOSTS_PATH: &str = "ghosts.ron";
+// seconds between recorded positions
+const SAMPLE_INTERVAL: f32 = 0.05;
+// pickups per split
+const MILESTONE: u32 = 10;
+// seconds a split stays next to the timer
+const DELTA_SHOWN: f32 = 3.;
+// just under the player
+const GHOST_Z: f32 = 1.9;
+
+// A run's path and split times. Positions are in screen sizes so a ghost plays back at any resolution.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct GhostRun {
+    // (time, x, y)
+    samples: Vec<(f32, f32, f32)>,
+    // time at every MILESTONE pickups
+    milestones: Vec<f32>,
+}
+
+impl GhostRun {
+    fn position(&self, time: f32) -> Option<Vec2> {
+        let next = self.samples.partition_point(|&(t, _, _)| t <= time);
+        if next == 0 || next >= self.samples.len() {
+            return None;
+        }
+        let (t0, x0, y0) = self.samples[next - 1];
+        let (t1, x1, y1) = self.samples[next];
+        let blend = ((time - t0) / (t1 - t0)).clamp(0., 1.);
+        Some(Vec2::new(x0, y0).lerp(Vec2::new(x1, y1), blend))
+    }
+}
+
+// The best run's path per board, replaced whenever a run takes first place.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct GhostLibrary {
+    runs: HashMap<String, GhostRun>,
+}
+
+impl GhostLibrary {
+    pub fn load() -> Self {
+        load_ron(GHOSTS_PATH)
+    }
+
+    pub fn save(&self) {
+        save_ron(GHOSTS_PATH, self);
+    }
+
+    pub fn store(&mut self, config: &RunConfig, daily: &DailyChallenge, run: GhostRun) {
+        if config.mode == GameMode::Daily {
+            // yesterday's layout is gone, so is its ghost
+            self.runs.retain(|key, _| !key.starts_with("Daily/"));
+        }
+        self.runs.insert(ghost_key(config, daily), run);
+    }
+
+    fn get(&self, config: &RunConfig, daily: &DailyChallenge) -> Option<&GhostRun> {
+        self.runs.get(&ghost_key(config, daily))
+    }
+}
+
+// the board a run ranks on, daily ghosts only race on the day they were set
+fn ghost_key(config: &RunConfig, daily: &DailyChallenge) -> String {
+    match config.mode {
+        GameMode::Daily => format!("{}/{}", board_key(config), daily.date),
+        _ => board_key(config),
+    }
+}
+
+#[derive(Resource)]
+pub struct GhostSettings {
+    pub enabled: bool,
+    pub alpha: f32,
+}
+
+impl Default for GhostSettings {
+    fn default() -> Self {
+        GhostSettings {
+            enabled: true,
+            alpha: 0.35,
+        }
+    }
+}
+
+// The run in progress, kept so it can become the next ghost.
+#[derive(Resource, Default)]
+pub struct GhostRecorder {
+    pub run: GhostRun,
+    // live minus ghost time at the last split, so negative is ahead, and when it was reached
+    delta: Option<(f32, f32)>,
+}
+
+#[derive(Component)]
+pub struct Ghost;
+
+pub fn reset_ghost(mut recorder: ResMut<GhostRecorder>) {
+    *recorder = GhostRecorder::default();
+}
+
+pub fn record_ghost(
+    time: Res<GameTime>,
+    windows: Res<Windows>,
+    stats: Res<RunStats>,
+    config: Res<RunConfig>,
+    daily: Res<DailyChallenge>,
+    library: Res<GhostLibrary>,
+    mut recorder: ResMut<GhostRecorder>,
+    player_query: Query<(&Transform, &Player)>,
+) {
+    let window = windows.get_primary().unwrap();
+    let due = match recorder.run.samples.last() {
+        Some(&(t, _, _)) => time.value - t >= SAMPLE_INTERVAL,
+        None => true,
+    };
+    if due {
+        // in co-op the first seat's path is the one kept
+        if let Some((player, _)) = player_query.iter().find(|(_, player)| player.seat == 0) {
+            recorder.run.samples.push((
+                time.value,
+                player.translation.x / window.width(),
+                player.translation.y / window.height(),
+            ));
+        }
+    }
+
+    let reached = (stats.pickups / MILESTONE) as usize;
+    while recorder.run.milestones.len() < reached {
+        let split = recorder.run.milestones.len();
+        recorder.run.milestones.push(time.value);
+        if let Some(ghost_time) = library.get(&config, &daily).and_then(|ghost| ghost.milestones.get(split)) {
+            recorder.delta = Some((time.value - ghost_time, time.value));
+        }
+    }
+}
+
+pub fn play_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<GhostSettings>,
+    library: Res<GhostLibrary>,
+    config: Res<RunConfig>,
+    daily: Res<DailyChallenge>,
+    time: Res<GameTime>,
+    theme: Res<Theme>,
+    size: Res<Sizes>,
+    windows: Res<Windows>,
+    mut ghost_query: Query<(&mut Transform, &mut Visibility, &Handle<ColorMaterial>), With<Ghost>>,
+) {
+    let (mut transform, mut visibility, handle) = match ghost_query.get_single_mut() {
+        Ok(ghost) => ghost,
+        Err(_) => {
+            commands.spawn(MaterialMesh2dBundle {
+                mesh: meshes.add(shape::Circle::new(size.player).into()).into(),
+                material: materials.add(ColorMaterial::from(theme.player_rim)),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(Ghost);
+            return;
+        }
+    };
+
+    let window = windows.get_primary().unwrap();
+    let position = library.get(&config, &daily)
+        .filter(|_| settings.enabled)
+        .and_then(|ghost| ghost.position(time.value));
+    let position = match position {
+        Some(position) => position,
+        None => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+
+    visibility.is_visible = true;
+    transform.translation = (position * Vec2::new(window.width(), window.height())).extend(GHOST_Z);
+    if let Some(material) = materials.get_mut(handle) {
+        material.color = theme.player_rim;
+        material.color.set_a(theme.player_rim.a() * settings.alpha);
+    }
+}
+
+pub fn hide_ghost(
+    mut ghost_query: Query<&mut Visibility, With<Ghost>>,
+) {
+    for mut visibility in ghost_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+// The split next to the timer, fading out after a few seconds.
+pub fn show_ghost_delta(
+    recorder: Res<GhostRecorder>,
+    settings: Res<GhostSettings>,
+    time: Res<GameTime>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    mut query: Query<&mut Text, With<GhostDeltaText>>,
+) {
+    let shown = recorder.delta
+        .filter(|_| settings.enabled)
+        .map(|(delta, at)| (delta, 1. - (time.value - at) / DELTA_SHOWN))
+        .filter(|&(_, alpha)| alpha > 0.);
+    for mut text in query.iter_mut() {
+        match shown {
+            Some((delta, alpha)) => {
+                text.sections[0].value = locale.format("ghost_delta", &controls, &[("delta", format!("{:+.1}", delta))]);
+                text.sections[0].style.color = theme.text;
+                text.sections[0].style.color.set_a(theme.text.a() * alpha);
+            }
+            None if !text.sections[0].value.is_empty() => text.sections[0].value.clear(),
+            None => {}
+        }
+    }
+}
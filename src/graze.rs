@@ -0,0 +1,84 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    Collider, Orb, Player, Score,
+    coop::{Down, PlayerHit},
+    particles::{Emitter, ParticleBurst},
+    stats::RunStats,
+    theme::Theme,
+};
+
+#[derive(Resource)]
+pub struct GrazeSettings {
+    // how close counts as a graze, gap between the edges in player widths
+    pub margin: f32,
+    pub points: i32,
+}
+
+impl Default for GrazeSettings {
+    fn default() -> Self {
+        GrazeSettings {
+            margin: 0.75,
+            points: 5,
+        }
+    }
+}
+
+// (player, orb) pairs currently inside the graze band, true once the pass is spoiled by a hit or a touch.
+#[derive(Resource, Default)]
+pub struct Grazing(HashMap<(Entity, Entity), bool>);
+
+// Pays out when the player slips back out of the band around a dangerous orb, unhurt.
+pub fn graze(
+    settings: Res<GrazeSettings>,
+    theme: Res<Theme>,
+    mut grazing: ResMut<Grazing>,
+    mut hits: EventReader<PlayerHit>,
+    mut score: ResMut<Score>,
+    mut stats: ResMut<RunStats>,
+    mut particle_writer: EventWriter<ParticleBurst>,
+    mut player_query: Query<(Entity, &Transform, &Collider, &mut Player), Without<Down>>,
+    orb_query: Query<(Entity, &Transform, &Collider), With<Orb>>,
+) {
+    // a hit from any orb spoils every pass the player is in the middle of
+    for PlayerHit(hit, _) in hits.iter() {
+        for ((player, _), spoiled) in grazing.0.iter_mut() {
+            if player == hit {
+                *spoiled = true;
+            }
+        }
+    }
+
+    for (player_entity, player, player_collider, mut scorer) in player_query.iter_mut() {
+        // orbs this player can hunt aren't a risk
+        if score.can_hunt(scorer.seat) {
+            grazing.0.retain(|(player, _), _| *player != player_entity);
+            continue;
+        }
+        let band = settings.margin * player_collider.0 * 2.;
+
+        for (orb, transform, collider) in orb_query.iter() {
+            let offset = player.translation - transform.translation;
+            let gap = offset.length() - player_collider.0 - collider.0;
+
+            if gap <= 0. {
+                // touching isn't a near miss, whether or not it cost a life
+                grazing.0.insert((player_entity, orb), true);
+            } else if gap <= band {
+                grazing.0.entry((player_entity, orb)).or_insert(false);
+            } else if grazing.0.remove(&(player_entity, orb)) == Some(false) {
+                score.points += settings.points;
+                scorer.points += settings.points;
+                stats.near_misses += 1;
+                particle_writer.send(ParticleBurst {
+                    position: player.translation - offset.normalize_or_zero() * player_collider.0,
+                    // flick along the orb's path, the way it brushed past
+                    direction: Vec2::NEG_X,
+                    scale: player_collider.0,
+                    emitter: Emitter::graze(theme.spark),
+                });
+            }
+        }
+    }
+    grazing.0.retain(|(player, orb), _| player_query.contains(*player) && orb_query.contains(*orb));
+}
@@ -0,0 +1,241 @@
+use bevy::{
+    prelude::*,
+    sprite::MaterialMesh2dBundle,
+};
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{
+    Dist, IDmin, Orb, PickID, Pickup, Player, Sizes,
+    accessibility::Accessibility,
+    coop::Down,
+    theme::{Paint, Theme},
+};
+
+// above pickups and orbs, under particles
+const ARROW_Z: f32 = 5.5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TargetRule {
+    Nearest,
+    // nearest, but pickups close to an orb count as further away
+    Safest,
+    // nearest, but pickups with others around them count as closer, good for combos
+    MostValuable,
+}
+
+impl TargetRule {
+    pub fn next(self) -> Self {
+        match self {
+            TargetRule::Nearest => TargetRule::Safest,
+            TargetRule::Safest => TargetRule::MostValuable,
+            TargetRule::MostValuable => TargetRule::Nearest,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            TargetRule::Nearest => "target_nearest",
+            TargetRule::Safest => "target_safest",
+            TargetRule::MostValuable => "target_most_valuable",
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Guidance {
+    pub rule: TargetRule,
+    // the arrow fades in between these distances to the target, in screen widths
+    pub fade: (f32, f32),
+    // pickups this close to each other count as neighbours for MostValuable, in screen widths
+    pub cluster: f32,
+}
+
+impl Default for Guidance {
+    fn default() -> Self {
+        Guidance {
+            rule: TargetRule::Nearest,
+            fade: (0.08, 0.35),
+            cluster: 0.15,
+        }
+    }
+}
+
+// Shared ring materials, pickups swap between them instead of respawning their ring.
+#[derive(Resource)]
+pub struct GuideMaterials {
+    ring: Handle<ColorMaterial>,
+    target: Handle<ColorMaterial>,
+}
+
+impl FromWorld for GuideMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let theme = world.resource::<Theme>().clone();
+        let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+        GuideMaterials {
+            ring: materials.add(ColorMaterial::from(theme.pickup_ring)),
+            target: materials.add(ColorMaterial::from(theme.pickup_target)),
+        }
+    }
+}
+
+// The ring around a pickup that lights up when it is the target.
+#[derive(Component)]
+pub struct TargetRing;
+
+#[derive(Component)]
+pub struct GuideArrow;
+
+pub fn select_target(
+    guidance: Res<Guidance>,
+    guide_materials: Res<GuideMaterials>,
+    accessibility: Res<Accessibility>,
+    theme: Res<Theme>,
+    windows: Res<Windows>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut min_id: ResMut<IDmin>,
+    mut dist: ResMut<Dist>,
+    player_query: Query<&Transform, (With<Player>, Without<Down>)>,
+    pick_query: Query<(&Transform, &PickID), With<Pickup>>,
+    orb_query: Query<&Transform, With<Orb>>,
+    mut ring_query: Query<(&Parent, &mut Handle<ColorMaterial>, &mut Paint, &mut Transform), (With<TargetRing>, Without<Pickup>, Without<Player>, Without<Orb>)>,
+) {
+    let window = windows.get_primary().unwrap();
+    let cluster = guidance.cluster * window.width();
+
+    let mut best = None;
+    if !player_query.is_empty() {
+        for (pick, pick_id) in pick_query.iter() {
+            // in co-op a pickup is as close as the nearest player
+            let distance = player_query.iter()
+                .map(|player| player.translation.distance(pick.translation))
+                .fold(f32::INFINITY, f32::min);
+            let cost = match guidance.rule {
+                TargetRule::Nearest => distance,
+                TargetRule::Safest => {
+                    let clearance = orb_query.iter()
+                        .map(|orb| orb.translation.distance(pick.translation))
+                        .fold(f32::INFINITY, f32::min);
+                    distance * (1. + window.width() / clearance.max(1.))
+                }
+                TargetRule::MostValuable => {
+                    let neighbours = pick_query.iter()
+                        .filter(|(other, _)| other.translation.distance(pick.translation) < cluster)
+                        .count();
+                    // the pickup itself is in the count, so a lone pickup costs its plain distance
+                    distance / neighbours as f32
+                }
+            };
+            match best {
+                Some((best_cost, _, _)) if best_cost <= cost => {}
+                _ => best = Some((cost, distance, pick_id.number)),
+            }
+        }
+    }
+    let (target, distance) = best.map_or((-1, f32::INFINITY), |(_, distance, id)| (id, distance));
+    min_id.value = target;
+    dist.value = distance;
+
+    // the shared materials aren't painted while no ring uses them, catch up on theme changes
+    if theme.is_changed() {
+        if let Some(material) = materials.get_mut(&guide_materials.ring) {
+            material.color = theme.pickup_ring;
+        }
+        if let Some(material) = materials.get_mut(&guide_materials.target) {
+            material.color = theme.pickup_target;
+        }
+    }
+
+    // the target ring is drawn bigger so it doesn't rely on color alone
+    let target_scale = if accessibility.shape_cues { 1.5 } else { 1. };
+
+    for (parent, mut handle, mut paint, mut transform) in ring_query.iter_mut() {
+        let is_target = matches!(pick_query.get(parent.get()), Ok((_, pick_id)) if pick_id.number == target);
+        let (wanted, wanted_paint, scale) = if is_target {
+            (&guide_materials.target, Paint::PickupTarget, target_scale)
+        } else {
+            (&guide_materials.ring, Paint::PickupRing, 1.)
+        };
+        if *handle != *wanted {
+            *handle = wanted.clone();
+            *paint = wanted_paint;
+        }
+        if transform.scale.x != scale {
+            transform.scale = Vec3::splat(scale);
+        }
+    }
+    min_id.last = min_id.value;
+}
+
+pub fn hide_guide(
+    mut arrow_query: Query<&mut Visibility, With<GuideArrow>>,
+) {
+    for mut visibility in arrow_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+}
+
+// Points from the player at the target, pinned to the screen edge when the target is off screen.
+pub fn point_guide(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    guidance: Res<Guidance>,
+    theme: Res<Theme>,
+    size: Res<Sizes>,
+    dist: Res<Dist>,
+    min_id: Res<IDmin>,
+    windows: Res<Windows>,
+    player_query: Query<&Transform, (With<Player>, Without<Down>)>,
+    pick_query: Query<(&Transform, &PickID), With<Pickup>>,
+    mut arrow_query: Query<(&mut Transform, &mut Visibility, &Handle<ColorMaterial>), (With<GuideArrow>, Without<Player>, Without<Pickup>)>,
+) {
+    let (mut transform, mut visibility, handle) = match arrow_query.get_single_mut() {
+        Ok(arrow) => arrow,
+        Err(_) => {
+            commands.spawn(MaterialMesh2dBundle {
+                mesh: meshes.add(shape::RegularPolygon::new(size.player * 0.45, 3).into()).into(),
+                material: materials.add(ColorMaterial::from(theme.pickup_target)),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(GuideArrow);
+            return;
+        }
+    };
+
+    let window = windows.get_primary().unwrap();
+    let target = pick_query.iter().find(|(_, pick_id)| pick_id.number == min_id.value);
+    // points from whichever player is closest to the target
+    let player = target.and_then(|(target, _)| player_query.iter()
+        .min_by(|a, b| a.translation.distance_squared(target.translation).total_cmp(&b.translation.distance_squared(target.translation))));
+    let (player, target) = match (player, target) {
+        (Some(player), Some((target, _))) => (player.translation, target.translation),
+        _ => {
+            visibility.is_visible = false;
+            return;
+        }
+    };
+
+    let direction = (target - player).truncate().normalize_or_zero();
+    let half = Vec2::new(window.width(), window.height()) / 2. - size.player;
+    let on_screen = target.x.abs() <= half.x && target.y.abs() <= half.y;
+    let position = if on_screen {
+        player.truncate() + direction * size.player * 1.8
+    } else {
+        target.truncate().clamp(-half, half)
+    };
+
+    // close targets need no pointing, the arrow fades out as the player gets there
+    let (near, far) = guidance.fade;
+    let alpha = ((dist.value / window.width() - near) / (far - near)).clamp(0., 1.);
+    let alpha = if on_screen { alpha } else { 1. };
+
+    visibility.is_visible = alpha > 0.;
+    transform.translation = position.extend(ARROW_Z);
+    // the triangle's tip starts out pointing up
+    transform.rotation = Quat::from_rotation_z(direction.y.atan2(direction.x) - FRAC_PI_2);
+    if let Some(material) = materials.get_mut(handle) {
+        material.color = theme.pickup_target;
+        material.color.set_a(theme.pickup_target.a() * alpha);
+    }
+}
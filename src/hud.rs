@@ -0,0 +1,289 @@
+use bevy::{prelude::*, window::WindowResized};
+
+use crate::{
+    Orb, Player, Score, ScoreText, TimeText, VolumeText,
+    combo::Combo,
+    controls::Controls,
+    i18n::Locale,
+    theme::{TextPaint, Theme},
+};
+
+// text height relative to the shorter window side, so ultrawide and portrait both read
+const FONT_RATIO: f32 = 1. / 22.5;
+const BAR_RATIO: f32 = 1. / 120.;
+// gap under the HUD and around the bar, relative to the window height
+const BOTTOM_RATIO: f32 = 1. / 64.;
+const BAR_MARGIN_RATIO: f32 = 1. / 128.;
+
+#[derive(Component)]
+pub struct HudText;
+#[derive(Component)]
+pub struct HudRoot;
+// a left margin, this share of the window width
+#[derive(Component)]
+pub struct HudGap(f32);
+#[derive(Component)]
+pub struct ProgressTrack;
+#[derive(Component)]
+pub struct ProgressFill;
+#[derive(Component)]
+pub struct OrbsText;
+#[derive(Component)]
+pub struct ComboText;
+// split against the ghost, filled in by the ghost module
+#[derive(Component)]
+pub struct GhostDeltaText;
+// co-op lives, filled in by the coop module
+#[derive(Component)]
+pub struct LivesText;
+
+fn font_size(width: f32, height: f32) -> f32 {
+    width.min(height) * FONT_RATIO
+}
+
+// one third of the bottom row, text pushed to the given side
+fn column(justify: JustifyContent) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_grow: 1.,
+            flex_basis: Val::Px(0.),
+            justify_content: justify,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+pub fn spawn_hud(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    window: &Window,
+    theme: &Theme,
+    score: &Score,
+    volume: i32,
+) {
+    let style = TextStyle {
+        font,
+        font_size: font_size(window.width(), window.height()),
+        color: theme.text,
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(0.),
+                    left: Val::Px(0.),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                // bevy's column direction runs bottom to top, reversed reads top to bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Stretch,
+                padding: UiRect {
+                    left: Val::Percent(100. / 16.),
+                    right: Val::Percent(100. / 16.),
+                    bottom: Val::Px(window.height() * BOTTOM_RATIO),
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        })
+        .insert(HudRoot)
+        .with_children(|hud| {
+            // remaining orbs, only shown in the hunter phase, and co-op lives
+            hud.spawn(NodeBundle {
+                style: Style {
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                row.spawn(TextBundle::from_section("", style.clone()))
+                    .insert(HudText)
+                    .insert(OrbsText)
+                    .insert(TextPaint);
+                row.spawn(TextBundle::from_section("", style.clone()).with_style(Style {
+                    margin: UiRect::left(Val::Px(window.width()/50.)),
+                    ..default()
+                }))
+                    .insert(HudGap(1. / 50.))
+                    .insert(HudText)
+                    .insert(LivesText)
+                    .insert(TextPaint);
+            });
+
+            // progress toward the score goal
+            hud.spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Px(window.height() * BAR_RATIO)),
+                    margin: UiRect::vertical(Val::Px(window.height() * BAR_MARGIN_RATIO)),
+                    ..default()
+                },
+                background_color: track_color(theme).into(),
+                ..default()
+            })
+            .insert(ProgressTrack)
+            .with_children(|track| {
+                track.spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(score.progress() * 100.), Val::Percent(100.)),
+                        ..default()
+                    },
+                    background_color: theme.text.into(),
+                    ..default()
+                })
+                .insert(ProgressFill);
+            });
+
+            // score | time | volume, equal columns so the timer sits in the true center
+            hud.spawn(NodeBundle {
+                style: Style {
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                row.spawn(column(JustifyContent::FlexStart)).with_children(|cell| {
+                    cell.spawn(TextBundle::from_section(score.points.to_string(), style.clone()))
+                        .insert(HudText)
+                        .insert(ScoreText)
+                        .insert(TextPaint);
+                    cell.spawn(TextBundle::from_section("", style.clone()).with_style(Style {
+                        margin: UiRect::left(Val::Px(window.width()/100.)),
+                        ..default()
+                    }))
+                        .insert(HudGap(1. / 100.))
+                        .insert(HudText)
+                        .insert(ComboText);
+                });
+                row.spawn(column(JustifyContent::Center)).with_children(|cell| {
+                    // the split hangs off the timer's right edge, out of the flow so the timer stays centered
+                    cell.spawn(NodeBundle::default()).with_children(|timer| {
+                        timer.spawn(TextBundle::from_section("0.0", style.clone()))
+                            .insert(HudText)
+                            .insert(TimeText)
+                            .insert(TextPaint);
+                        timer.spawn(TextBundle::from_section("", style.clone()).with_style(Style {
+                            position_type: PositionType::Absolute,
+                            position: UiRect {
+                                left: Val::Percent(100.),
+                                bottom: Val::Px(0.),
+                                ..default()
+                            },
+                            margin: UiRect::left(Val::Px(window.width()/100.)),
+                            ..default()
+                        }))
+                            .insert(HudGap(1. / 100.))
+                            .insert(HudText)
+                            .insert(GhostDeltaText);
+                    });
+                });
+                row.spawn(column(JustifyContent::FlexEnd)).with_children(|cell| {
+                    cell.spawn(TextBundle::from_section(volume.to_string() + "%", style.clone()))
+                        .insert(HudText)
+                        .insert(VolumeText)
+                        .insert(TextPaint);
+                });
+            });
+        });
+}
+
+fn track_color(theme: &Theme) -> Color {
+    let mut color = theme.text;
+    color.set_a(color.a() * 0.3);
+    color
+}
+
+pub fn update_hud(
+    score: Res<Score>,
+    combo: Res<Combo>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    orb_query: Query<(), With<Orb>>,
+    mut fill_query: Query<(&mut Style, &mut BackgroundColor), (With<ProgressFill>, Without<ProgressTrack>)>,
+    mut track_query: Query<&mut BackgroundColor, With<ProgressTrack>>,
+    mut orbs_query: Query<(&mut Text, &mut Visibility), With<OrbsText>>,
+    mut combo_query: Query<(&mut Text, &mut Visibility), (With<ComboText>, Without<OrbsText>)>,
+    player_query: Query<&Player>,
+) {
+    for (mut style, mut background) in fill_query.iter_mut() {
+        style.size.width = Val::Percent(score.progress() * 100.);
+        background.0 = theme.text;
+    }
+    for mut background in track_query.iter_mut() {
+        background.0 = track_color(&theme);
+    }
+
+    let hunting = score.hunting();
+    for (mut text, mut visibility) in orbs_query.iter_mut() {
+        visibility.is_visible = hunting;
+        if hunting {
+            let count = orb_query.iter().count().to_string();
+            text.sections[0].value = locale.format("orbs_left", &controls, &[("count", count)]);
+        }
+    }
+
+    // the multiplier fades out as its window runs down, versus shows each rival's own
+    let rules = &combo.rules;
+    let (value, remaining) = if score.race {
+        let mut players: Vec<_> = player_query.iter()
+            .filter(|player| player.chain.multiplier(rules) > 1)
+            .collect();
+        players.sort_by_key(|player| player.seat);
+        let value = players.iter()
+            .map(|player| locale.format("combo_player", &controls, &[
+                ("player", (player.seat + 1).to_string()),
+                ("multiplier", player.chain.multiplier(rules).to_string()),
+            ]))
+            .collect::<Vec<_>>()
+            .join("  ");
+        (value, players.iter().map(|player| player.chain.remaining(rules)).fold(0., f32::max))
+    } else if combo.shared.multiplier(rules) > 1 {
+        let multiplier = combo.shared.multiplier(rules).to_string();
+        (locale.format("combo", &controls, &[("multiplier", multiplier)]), combo.shared.remaining(rules))
+    } else {
+        (String::new(), 0.)
+    };
+    for (mut text, mut visibility) in combo_query.iter_mut() {
+        visibility.is_visible = !value.is_empty();
+        text.sections[0].value = value.clone();
+        let mut color = theme.text;
+        color.set_a(color.a() * (0.3 + 0.7 * remaining));
+        text.sections[0].style.color = color;
+    }
+}
+
+// keeps text, bar and spacing proportions right when the window changes size
+pub fn layout_hud(
+    mut resize_events: EventReader<WindowResized>,
+    mut text_query: Query<&mut Text, With<HudText>>,
+    mut track_query: Query<&mut Style, With<ProgressTrack>>,
+    mut root_query: Query<&mut Style, (With<HudRoot>, Without<ProgressTrack>)>,
+    mut gap_query: Query<(&mut Style, &HudGap), (Without<HudRoot>, Without<ProgressTrack>)>,
+) {
+    if let Some(resized) = resize_events.iter().last() {
+        let size = font_size(resized.width, resized.height);
+        for mut text in text_query.iter_mut() {
+            for section in text.sections.iter_mut() {
+                section.style.font_size = size;
+            }
+        }
+        for mut style in track_query.iter_mut() {
+            style.size.height = Val::Px(resized.height * BAR_RATIO);
+            style.margin = UiRect::vertical(Val::Px(resized.height * BAR_MARGIN_RATIO));
+        }
+        for mut style in root_query.iter_mut() {
+            style.padding.bottom = Val::Px(resized.height * BOTTOM_RATIO);
+        }
+        for (mut style, HudGap(share)) in gap_query.iter_mut() {
+            style.margin.left = Val::Px(resized.width * share);
+        }
+    }
+}
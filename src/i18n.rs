@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::controls::Controls;
+
+// one .ron file per language, English is built in and fills any gaps
+const LANG_DIR: &str = "assets/lang";
+const DEFAULT_FONT: &str = "fonts/FiraSans-Bold.ttf";
+
+#[derive(Deserialize)]
+pub struct Language {
+    pub name: String,
+    // font for scripts FiraSans doesn't cover, relative to assets/, the table is skipped until the file is there
+    #[serde(default)]
+    pub font: Option<String>,
+    pub strings: HashMap<String, String>,
+}
+
+impl Language {
+    fn english() -> Self {
+        let strings = [
+            ("title", "Proto"),
+            ("menu_play", "Play"),
+            ("menu_mode", "Mode: {value}"),
+            ("menu_difficulty", "Difficulty: {value}"),
+            ("menu_players", "Players: {value}"),
+            ("menu_high_scores", "High Scores"),
+            ("menu_controls", "Controls"),
+            ("menu_quit", "Quit"),
+            ("mode_classic", "Classic"),
+            ("mode_time_attack", "Time Attack"),
+            ("mode_endless", "Endless"),
+            ("mode_daily", "Daily Challenge"),
+            ("daily_today", "Today ({date}): {orbs} orbs, {pickups} pickups, {behavior} orbs, speed-up x{ramp}"),
+            ("daily_done", "Today's attempt is recorded, further runs are practice"),
+            ("daily_header", "Daily challenge history"),
+            ("daily_won", "{date}  won in {time}s"),
+            ("daily_lost", "{date}  defeated after {time}s, {points} points"),
+            ("daily_official", "Recorded as today's attempt"),
+            ("daily_practice", "Practice run, not recorded"),
+            ("behavior_straight", "straight"),
+            ("behavior_wave", "weaving"),
+            ("behavior_homing", "homing"),
+            ("difficulty_easy", "Easy"),
+            ("difficulty_normal", "Normal"),
+            ("difficulty_hard", "Hard"),
+            ("players_solo", "Solo"),
+            ("players_coop", "Two-player co-op"),
+            ("players_versus", "Two-player versus"),
+            ("scores_header", "Fastest victories - {mode}, {difficulty}"),
+            ("scores_empty", "No victories yet"),
+            ("scores_entry", "{rank}.  {time}s"),
+            ("scores_header_points", "Best scores - {mode}, {difficulty}"),
+            ("scores_entry_points", "{rank}.  {points}"),
+            ("control_up", "Up"),
+            ("control_down", "Down"),
+            ("control_left", "Left"),
+            ("control_right", "Right"),
+            ("control_p2_up", "Player 2 up"),
+            ("control_p2_down", "Player 2 down"),
+            ("control_p2_left", "Player 2 left"),
+            ("control_p2_right", "Player 2 right"),
+            ("control_confirm", "Confirm / Pause"),
+            ("control_mute", "Mute"),
+            ("control_theme", "Next theme"),
+            ("control_color_vision", "Color vision"),
+            ("control_high_contrast", "High contrast"),
+            ("control_shape_cues", "Shape cues"),
+            ("control_language", "Next language"),
+            ("controls_pad", "Gamepad: D-pad to move, A to confirm, B to go back"),
+            ("defeat", "Defeat!"),
+            ("victory", "Victory!"),
+            ("time_up", "Time's up!"),
+            ("menu_play_again", "Play Again"),
+            ("stats_points", "Points: {value}"),
+            ("stats_time", "Time survived: {value}s"),
+            ("stats_pickups", "Pickups collected: {value}"),
+            ("stats_orbs_destroyed", "Orbs destroyed: {value}"),
+            ("stats_pickups_lost", "Pickups lost to orbs: {value}"),
+            ("stats_near_misses", "Near misses: {value}"),
+            ("stats_distance", "Distance traveled: {value} screens"),
+            ("stats_closest_call", "Closest call: {value} player widths"),
+            ("stats_peak_orb_speed", "Peak orb speed: {value} screens/s"),
+            ("orbs_left", "Orbs: {count}"),
+            ("combo", "x{multiplier}"),
+            ("combo_player", "P{player} x{multiplier}"),
+            ("paused", "Paused"),
+            ("pause_stats", "Score {score}   Time {time}"),
+            ("menu_resume", "Resume"),
+            ("menu_restart", "Restart"),
+            ("menu_settings", "Settings"),
+            ("menu_quit_to_title", "Quit to Title"),
+            ("menu_back", "Back"),
+            ("setting_theme", "Theme: {value}"),
+            ("setting_language", "Language: {value}"),
+            ("setting_color_vision", "Color vision: {value}"),
+            ("setting_high_contrast", "High contrast: {value}"),
+            ("setting_shape_cues", "Shape cues: {value}"),
+            ("setting_reduce_motion", "Reduce motion: {value}"),
+            ("setting_targeting", "Guidance: {value}"),
+            ("setting_ghost", "Ghost: {value}"),
+            ("ghost_delta", "{delta}s"),
+            ("lives", "P{player}: {lives}"),
+            ("versus_status", "P{player}: {points} pts, {lives} lives"),
+            ("versus_p1_wins", "Player 1 wins!"),
+            ("versus_p2_wins", "Player 2 wins!"),
+            ("versus_draw", "Draw!"),
+            ("versus_points", "Player {player}: {points} points"),
+            ("net_waiting", "Hosting on {address}, waiting for a player to join"),
+            ("net_joined", "Hosting on {address}, {peer} joined"),
+            ("net_connecting", "Joining {address}..."),
+            ("net_connected", "Connected to {address}, waiting for the host to start"),
+            ("target_nearest", "Nearest"),
+            ("target_safest", "Safest"),
+            ("target_most_valuable", "Most valuable"),
+            ("vision_typical", "Typical"),
+            ("vision_deuteranopia", "Deuteranopia"),
+            ("vision_protanopia", "Protanopia"),
+            ("vision_tritanopia", "Tritanopia"),
+            ("on", "On"),
+            ("off", "Off"),
+        ];
+        Language {
+            name: "English".to_string(),
+            font: None,
+            strings: strings.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+}
+
+// Marks a text whose first section comes from the string table, so it can be
+// rewritten when the language or a key binding changes.
+#[derive(Component)]
+pub struct Localized(pub &'static str);
+
+#[derive(Resource)]
+pub struct Locale {
+    languages: Vec<Language>,
+    index: usize,
+}
+
+impl Locale {
+    pub fn load() -> Self {
+        let mut languages = vec![Language::english()];
+
+        if let Ok(entries) = fs::read_dir(LANG_DIR) {
+            let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            for path in paths {
+                if path.extension().and_then(|ext| ext.to_str()) != Some("ron") {
+                    continue;
+                }
+                match fs::read_to_string(&path).map_err(|e| e.to_string())
+                    .and_then(|text| ron::from_str::<Language>(&text).map_err(|e| e.to_string()))
+                {
+                    Ok(language) => {
+                        // the default font may lack the script, which would show as empty boxes
+                        if let Some(font) = language.font.as_ref().filter(|font| !Path::new("assets").join(font).exists()) {
+                            warn!("skipping language {}, font {} not found", language.name, font);
+                            continue;
+                        }
+                        languages.push(language);
+                    }
+                    Err(e) => warn!("skipping language {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        Locale { languages, index: 0 }
+    }
+
+    pub fn next(&mut self) {
+        self.index = (self.index + 1) % self.languages.len();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.languages[self.index].name
+    }
+
+    pub fn font(&self, asset_server: &AssetServer) -> Handle<Font> {
+        let path = self.languages[self.index].font.as_deref().unwrap_or(DEFAULT_FONT);
+        asset_server.load(path)
+    }
+
+    // Looks the key up in the current language, then English, then gives the key
+    // back. `{name}` placeholders are filled with the bound key names.
+    pub fn text(&self, key: &str, controls: &Controls) -> String {
+        let mut text = self.raw(key).to_string();
+        for (name, key_code) in controls.named() {
+            let placeholder = format!("{{{}}}", name);
+            if text.contains(&placeholder) {
+                text = text.replace(&placeholder, &self.key_name(key_code));
+            }
+        }
+        text
+    }
+
+    // like `text`, with extra `{name}` values such as counts
+    pub fn format(&self, key: &str, controls: &Controls, args: &[(&str, String)]) -> String {
+        let mut text = self.text(key, controls);
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), value);
+        }
+        text
+    }
+
+    // tables may translate key names as "key_Space", otherwise bevy's name is used
+    pub fn key_name(&self, key_code: KeyCode) -> String {
+        let name = format!("{:?}", key_code);
+        let lookup = format!("key_{}", name);
+        match self.lookup(&lookup) {
+            Some(translated) => translated.to_string(),
+            None => name,
+        }
+    }
+
+    fn raw<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        self.languages[self.index]
+            .strings
+            .get(key)
+            .or_else(|| self.languages[0].strings.get(key))
+            .map(|s| s.as_str())
+    }
+}
+
+pub fn cycle_language(
+    input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
+    mut locale: ResMut<Locale>,
+) {
+    if input.just_pressed(controls.language) {
+        locale.next();
+        info!("language: {}", locale.name());
+    }
+}
+
+// Rewrites localized texts and swaps fonts everywhere after a language or binding change.
+pub fn relocalize(
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    asset_server: Res<AssetServer>,
+    mut text_query: Query<(&mut Text, Option<&Localized>)>,
+) {
+    if !locale.is_changed() && !controls.is_changed() {
+        return;
+    }
+    let font = locale.font(&asset_server);
+    for (mut text, localized) in text_query.iter_mut() {
+        if let Some(Localized(key)) = localized {
+            text.sections[0].value = locale.text(key, &controls);
+        }
+        for section in text.sections.iter_mut() {
+            section.style.font = font.clone();
+        }
+    }
+}
@@ -1,15 +1,95 @@
 use bevy::{
     prelude::*,
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    log::LogPlugin,
+    render::settings::WgpuSettings,
+    winit::WinitPlugin,
+    ecs::system::SystemParam,
     sprite::MaterialMesh2dBundle,
     window::WindowMode::BorderlessFullscreen,
     audio::AudioSink,
     //diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
 };
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, time::Duration};
+
+mod accessibility;
+mod agent;
+mod autopilot;
+mod camera;
+mod combo;
+mod controls;
+mod coop;
+mod daily;
+mod director;
+mod ghost;
+mod graze;
+mod guidance;
+mod hud;
+mod i18n;
+mod menu;
+mod modes;
+mod net;
+mod particles;
+mod placement;
+mod scores;
+mod stats;
+mod starfield;
+mod storage;
+mod telemetry;
+mod theme;
+mod versus;
+use accessibility::{Accessibility, accessibility_keys, orb_cues, spin_dashes};
+use agent::{AgentEnv, serve_agent};
+use autopilot::{Autopilot, attract_mode, headless_window, soak_test, steer_bot};
+use combo::{Chain, Combo, decay_combo};
+use camera::{CameraEvent, CameraFx, CameraSettings, MainCamera, ScreenFlash, camera_effects, camera_events, spawn_camera};
+use controls::Controls;
+use coop::{Down, PlayerHit, core_paint, recover, start_position, take_hits, update_lives};
+use daily::{DailyChallenge, DailyHistory, record_daily};
+use director::{SpawnDirector, direct_spawns, reset_director};
+use ghost::{Ghost, GhostLibrary, GhostRecorder, GhostSettings, hide_ghost, play_ghost, record_ghost, reset_ghost, show_ghost_delta};
+use graze::{GrazeSettings, Grazing, graze};
+use guidance::{GuideArrow, GuideMaterials, Guidance, TargetRing, hide_guide, point_guide, select_target};
+use i18n::{Locale, cycle_language, relocalize};
+use menu::{
+    Menu, MenuEvent, Page, RestartRequest, despawn_menu, menu_navigation, rebuild_menu, run_actions,
+    settings_actions, spawn_overlay, spawn_pause_menu, spawn_results_menu, take_restart, title_actions,
+    update_menu_info, update_menu_labels,
+};
+use modes::{FixedSeed, GameRng, Stream, OrbBehavior, Players, RunConfig, RunModifiers, check_time_limit, prepare_run};
+use hud::{layout_hud, spawn_hud, update_hud};
+use net::{NetSession, apply_snapshot, authoritative, count_run, follow_host, receive_packets, send_input, send_snapshot};
+use particles::{Emitter, ParticleBurst, ParticlePool, emit_particles, update_particles};
+use placement::{Blocker, find_spot, screen_bounds, spot_in};
 use rand::Rng;
+use scores::{HighScores, record_run};
+use stats::RunStats;
+use starfield::{Star, Starfield, move_scene, spawn_stars, twinkle_stars};
+use telemetry::{TelemetryEvent, TelemetryLog, log_run_end, sample_positions, track_pause, write_telemetry};
+use theme::{Paint, Theme, ThemeLibrary, apply_theme, cycle_theme, sync_theme};
+use versus::{VersusResult, bump, decide_winner};
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
+    let autopilot = Autopilot::from_args();
+    let agent = AgentEnv::from_args();
+    let mut app = App::new();
+    if autopilot.headless() || agent.active() {
+        // no window or renderer, a fixed 60 Hz loop for the soak test, as fast as the agent steps otherwise
+        let wait = if agent.active() { Duration::ZERO } else { Duration::from_secs_f64(1. / 60.) };
+        let plugins = DefaultPlugins.build().disable::<WinitPlugin>().set(WindowPlugin {
+            add_primary_window: false,
+            exit_on_all_closed: false,
+            ..default()
+        });
+        let plugins = if agent.on_stdio() { plugins.disable::<LogPlugin>() } else { plugins };
+        app.insert_resource(WgpuSettings { backends: None, ..default() })
+            .insert_resource(ScheduleRunnerSettings::run_loop(wait))
+            .add_plugins(plugins)
+            .add_plugin(ScheduleRunnerPlugin)
+            .add_startup_system(headless_window);
+    } else {
+        app.add_plugins(DefaultPlugins.set(WindowPlugin {
             window: WindowDescriptor {
                 title: "Proto".to_string(),
                 mode: BorderlessFullscreen,
@@ -18,8 +98,39 @@ fn main() {
                 ..default()
             },
             ..default()
-        }))
+        }));
+    }
+    app
         .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
+        .insert_resource(ThemeLibrary::load())
+        .insert_resource(Theme::classic())
+        .init_resource::<GuideMaterials>()
+        .insert_resource(Guidance::default())
+        .insert_resource(Accessibility::default())
+        .insert_resource(Controls::default())
+        .insert_resource(Locale::load())
+        .insert_resource(Menu::default())
+        .insert_resource(RestartRequest::default())
+        .insert_resource(RunConfig::default())
+        .insert_resource(RunModifiers::default())
+        .insert_resource(GameRng::default())
+        .insert_resource(DailyChallenge::default())
+        .insert_resource(DailyHistory::load())
+        .insert_resource(HighScores::load())
+        .insert_resource(GhostLibrary::load())
+        .insert_resource(GhostSettings::default())
+        .insert_resource(GhostRecorder::default())
+        .insert_resource(RunStats::default())
+        .insert_resource(GrazeSettings::default())
+        .insert_resource(Combo::default())
+        .insert_resource(SpawnDirector::default())
+        .insert_resource(Grazing::default())
+        .insert_resource(VersusResult::default())
+        .insert_resource(NetSession::from_args())
+        .insert_resource(autopilot)
+        .insert_resource(agent)
+        .insert_resource(FixedSeed::default())
+        .insert_resource(TelemetryLog::open())
         .insert_resource(Sizes{
             player: 0.,
             star: 0.,
@@ -30,18 +141,18 @@ fn main() {
             player: 0.,
             star: 0.,
             orb: 0.,
+            ramp: 1.,
         })
         .insert_resource(Score{
-            value: 0,
+            goal: 0,
+            points: 0,
+            hunt_at: Some(100),
+            race: false,
+            hunter: None,
         })
         .insert_resource(Volume{
             value: 50,
         })
-        .insert_resource(OrbsRGB{
-            r: 1.,
-            g: 0.,
-            b: 0.,
-        })
         .insert_resource(GameTime{
             value: 0.,
         })
@@ -52,95 +163,232 @@ fn main() {
         .insert_resource(Dist{
             value: f32::INFINITY,
         })
+        .insert_resource(Starfield::default())
+        .insert_resource(ParticlePool::default())
+        .insert_resource(CameraSettings::default())
+        .insert_resource(CameraFx::default())
         
         .add_event::<CollisionEvent>()
         .add_event::<PickupCollision>()
+        .add_event::<PlayerHit>()
+        .add_event::<ParticleBurst>()
+        .add_event::<CameraEvent>()
+        .add_event::<MenuEvent>()
+        .add_event::<TelemetryEvent>()
         .add_state(GameState::Init)
         .add_system_set(
             SystemSet::on_enter(GameState::Init)
-                .with_system(setup_intro)
+                .with_system(teardown_game)
+                .with_system(setup_title.after(teardown_game))
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::Init)
+                .with_system(move_scene)
+                .with_system(twinkle_stars)
+                .with_system(menu_navigation.before(rebuild_menu))
+                .with_system(rebuild_menu)
+                .with_system(update_menu_labels.after(rebuild_menu))
+                .with_system(update_menu_info)
         )
         .add_system_set(
             SystemSet::on_exit(GameState::Init)
-                .with_system(delete_intro)
+                .with_system(despawn_menu)
+                .with_system(prepare_run.before(setup))
                 .with_system(setup)
+                .with_system(reset_director)
+                .with_system(reset_ghost)
+                .with_system(count_run)
         )
         .add_system_set(
-            SystemSet::on_update(GameState::Game)
-                .with_system(movement)
-                .with_system(orb_movement)
-                .with_system(detect_collisions)
-                .with_system(manage_collisions.after(detect_collisions))
-                .with_system(handle_pickup_collision.after(manage_collisions))
+            SystemSet::new()
+                .with_run_criteria(State::on_update(GameState::Game).label(InGame))
+                .with_system(update_lives)
                 .with_system(update_time)
                 .with_system(update_score)
                 .with_system(move_scene)
+                .with_system(twinkle_stars)
                 .with_system(update_volume)
                 .with_system(audio_control)
-                .with_system(nearest_pick)
-                .with_system(check_win)
+                .with_system(select_target.after(movement))
+                .with_system(point_guide.after(select_target))
+                .with_system(record_ghost.after(movement).after(update_time).after(manage_collisions))
+                .with_system(play_ghost.after(update_time))
+                .with_system(show_ghost_delta.after(record_ghost))
+                .with_system(update_hud)
+        )
+        // the simulation, which a networked client leaves to the host
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(RunCriteria::pipe(InGame, authoritative))
+                .with_system(steer_bot.before(movement))
+                .with_system(movement.after(receive_packets))
+                .with_system(orb_movement)
+                .with_system(detect_collisions)
+                .with_system(manage_collisions.after(detect_collisions))
+                .with_system(take_hits.after(manage_collisions))
+                .with_system(recover.before(take_hits))
+                .with_system(graze.after(movement).after(orb_movement).after(manage_collisions))
+                .with_system(decay_combo.before(manage_collisions))
+                .with_system(handle_pickup_collision.after(manage_collisions))
+                .with_system(check_win.after(take_hits))
+                .with_system(direct_spawns.after(update_time))
+                .with_system(check_time_limit.after(take_hits).after(update_time))
+                .with_system(sample_positions.after(movement).after(update_time))
         )
 
         .add_system_set(
             SystemSet::on_enter(GameState::Pause)
                 .with_system(toggle_music)
+                .with_system(spawn_pause_menu)
         )
         .add_system_set(
             SystemSet::on_update(GameState::Pause)
                 //.with_system(update_volume)
                 .with_system(audio_control)
+                .with_system(menu_navigation.before(rebuild_menu))
+                .with_system(rebuild_menu)
+                .with_system(update_menu_labels.after(rebuild_menu))
+                .with_system(update_menu_info)
         )
         .add_system_set(
             SystemSet::on_exit(GameState::Pause)
                 .with_system(toggle_music)
+                .with_system(despawn_menu)
         )
 
         .add_system_set(
             SystemSet::on_enter(GameState::Dead)
-                .with_system(dead_text)
+                .with_system(decide_winner.before(spawn_results_menu))
+                .with_system(spawn_results_menu)
+                .with_system(hide_guide)
+                .with_system(hide_ghost)
+                .with_system(record_run)
+                .with_system(log_run_end)
+                .with_system(record_daily)
         )
         .add_system_set(
             SystemSet::on_update(GameState::Dead)
                 .with_system(move_scene)
+                .with_system(twinkle_stars)
                 //.with_system(update_volume)
                 .with_system(audio_control)
+                .with_system(menu_navigation.before(rebuild_menu))
+                .with_system(rebuild_menu)
+                .with_system(update_menu_labels.after(rebuild_menu))
+                .with_system(update_menu_info)
         )
         .add_system_set(
             SystemSet::on_exit(GameState::Dead)
-                .with_system(delete_dead)
-                .with_system(reset_game)
+                .with_system(despawn_menu)
         )
 
         .add_system_set(
             SystemSet::on_enter(GameState::Victory)
-                .with_system(victory_text)
+                .with_system(hide_player)
+                .with_system(decide_winner.before(spawn_results_menu))
+                .with_system(spawn_results_menu)
+                .with_system(hide_ghost)
+                .with_system(record_run)
+                .with_system(log_run_end)
+                .with_system(record_daily)
         )
         .add_system_set(
             SystemSet::on_update(GameState::Victory)
                 .with_system(move_scene)
+                .with_system(twinkle_stars)
                 //.with_system(update_volume)
                 .with_system(audio_control)
+                .with_system(menu_navigation.before(rebuild_menu))
+                .with_system(rebuild_menu)
+                .with_system(update_menu_labels.after(rebuild_menu))
+                .with_system(update_menu_info)
         )
         .add_system_set(
             SystemSet::on_exit(GameState::Victory)
-                .with_system(delete_victory)
-                .with_system(reset_game)
+                .with_system(despawn_menu)
+                .with_system(show_player)
+        )
+
+        .add_system_set(
+            SystemSet::on_enter(GameState::TimeUp)
+                .with_system(decide_winner.before(spawn_results_menu))
+                .with_system(spawn_results_menu)
+                .with_system(hide_guide)
+                .with_system(hide_ghost)
+                .with_system(record_run)
+                .with_system(log_run_end)
+        )
+        .add_system_set(
+            SystemSet::on_update(GameState::TimeUp)
+                .with_system(move_scene)
+                .with_system(twinkle_stars)
+                .with_system(audio_control)
+                .with_system(menu_navigation.before(rebuild_menu))
+                .with_system(rebuild_menu)
+                .with_system(update_menu_labels.after(rebuild_menu))
+                .with_system(update_menu_info)
+        )
+        .add_system_set(
+            SystemSet::on_exit(GameState::TimeUp)
+                .with_system(despawn_menu)
         )
         //.add_system(toggle_cursor)
         .add_system(toggle_state)
+        // menu events are read once, here, whichever screen sent them
+        .add_system(settings_actions)
+        .add_system(title_actions)
+        .add_system(run_actions)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(take_restart)
+                .with_system(prepare_run.before(reset_game))
+                .with_system(reset_game)
+                .with_system(reset_director)
+                .with_system(reset_ghost)
+                .with_system(count_run)
+        )
+        .add_system(attract_mode.after(toggle_state).after(title_actions).after(run_actions))
+        .add_system(soak_test)
+        .add_system_to_stage(CoreStage::Last, serve_agent)
+        .add_system(track_pause)
+        .add_system_to_stage(CoreStage::PostUpdate, write_telemetry)
+        .add_system(receive_packets)
+        .add_system(follow_host.after(receive_packets))
+        .add_system(send_input.after(receive_packets))
+        .add_system(send_snapshot.after(receive_packets))
+        .add_system(apply_snapshot.after(follow_host).after(update_time))
+        .add_system(emit_particles)
+        .add_system(update_particles)
+        .add_system(camera_events)
+        .add_system(cycle_theme)
+        .add_system(accessibility_keys)
+        .add_system(sync_theme.after(cycle_theme).after(accessibility_keys))
+        .add_system(apply_theme.after(sync_theme))
+        .add_system(orb_cues.after(sync_theme))
+        .add_system(spin_dashes)
+        .add_system(layout_hud)
+        .add_system(cycle_language)
+        .add_system(relocalize.after(cycle_language))
+        .add_system(camera_effects.after(camera_events))
         .add_system(bevy::window::close_on_esc)
         //.add_plugin(LogDiagnosticsPlugin::default())
         //.add_plugin(FrameTimeDiagnosticsPlugin::default())
         .run();
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+// labels the in-game run criteria so the simulation set can pipe from it
+#[derive(RunCriteriaLabel)]
+struct InGame;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 enum GameState {
     Init,
     Game,
     Pause,
     Dead,
-    Victory
+    Victory,
+    // the time-attack clock ran out
+    TimeUp,
 }
 
 struct CollisionEvent(Entity, Entity);
@@ -148,7 +396,32 @@ struct CollisionEvent(Entity, Entity);
 struct PickupCollision(Entity);
 
 #[derive(Component)]
-struct Player;
+struct Player {
+    // 0 for the first player, 1 for the co-op partner or rival
+    seat: usize,
+    // pickups this player brought toward the goal, sizes their fill
+    pickups: i32,
+    // this player's own share of the points, what versus is decided on
+    points: i32,
+    lives: u32,
+    // seconds left of blinking invulnerability after losing a life
+    recovering: f32,
+    // this player's own pickup streak, only versus counts it
+    chain: Chain,
+}
+
+impl Player {
+    fn new(seat: usize, lives: u32) -> Self {
+        Player {
+            seat,
+            pickups: 0,
+            points: 0,
+            lives,
+            recovering: 0.,
+            chain: Chain::default(),
+        }
+    }
+}
 #[derive(Component)]
 struct Pickup;
 #[derive(Component)]
@@ -156,10 +429,8 @@ struct Orb;
 #[derive(Component)]
 struct OrbBorder;
 #[derive(Component)]
-struct Star;
-#[derive(Component)]
 struct Collider(f32);
-#[derive(Component, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Component, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
 enum CollideType {
     Player,
     Pickup,
@@ -172,12 +443,6 @@ struct TimeText;
 #[derive(Component)]
 struct VolumeText;
 #[derive(Component)]
-struct IntroText;
-#[derive(Component)]
-struct DeadText;
-#[derive(Component)]
-struct VictoryText;
-#[derive(Component)]
 struct PickID {
     number: i32,
 }
@@ -198,22 +463,45 @@ struct Speed {
     player: f32,
     star: f32,
     orb: f32,
+    // multiplier on the orb speed-up per pickup
+    ramp: f32,
 }
 #[derive(Resource)]
 struct Score {
-    value: i32,
+    // pickups toward the goal
+    goal: i32,
+    // what the player sees: pickups times the combo multiplier plus graze bonuses
+    points: i32,
+    // pickups needed before orbs can be hunted, None in modes without a hunter phase
+    hunt_at: Option<i32>,
+    // versus: the goal is each player's own pickups and only the first one there hunts
+    race: bool,
+    hunter: Option<usize>,
+}
+impl Score {
+    // like progress, for one player's part of the shared goal
+    fn share(&self, pickups: i32) -> f32 {
+        self.hunt_at.map_or(0., |hunt_at| (pickups as f32 / hunt_at as f32).clamp(0., 1.))
+    }
+    fn progress(&self) -> f32 {
+        self.hunt_at.map_or(0., |hunt_at| (self.goal as f32 / hunt_at as f32).clamp(0., 1.))
+    }
+    fn hunting(&self) -> bool {
+        matches!(self.hunt_at, Some(hunt_at) if self.goal >= hunt_at)
+    }
+    fn can_hunt(&self, seat: usize) -> bool {
+        self.hunting() && self.hunter.unwrap_or(seat) == seat
+    }
+    // true only for the pickup that completed the goal
+    fn goal_reached(&self) -> bool {
+        self.hunt_at == Some(self.goal)
+    }
 }
 #[derive(Resource)]
 struct Volume {
     value: i32,
 }
 
-#[derive(Resource)]
-struct OrbsRGB {
-    r: f32,
-    g: f32,
-    b: f32,
-}
 #[derive(Resource)]
 struct GameTime {
     value: f32,
@@ -230,52 +518,17 @@ struct Dist {
 #[derive(Resource)]
 struct MusicController(Handle<AudioSink>);
 
-fn victory_text(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut windows: ResMut<Windows>,
-    mut query: Query<Entity, With<RootNode>>,
+fn hide_player(
     mut player_query: Query<&mut Visibility, With<Player>>,
 ) {
-    let window = windows.get_primary_mut().unwrap();
-    let text_color = Color::Rgba {
-        red: 255.,
-        green: 255.,
-        blue: 255.,
-        alpha: 0.5,
-    };
-
-    for node in query.iter_mut(){
-        commands.entity(node).with_children(|parent|{
-            parent.spawn(
-                TextBundle::from_section(
-                    "Victory!\nPress Space To Play Again!",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: window.width()/20.,
-                        color: text_color,
-                    },
-                )
-                .with_text_alignment(TextAlignment::TOP_CENTER)
-            )
-            .insert(VictoryText);
-        });
-    }
-
     for mut player in player_query.iter_mut(){
         player.is_visible = false;
     }
 }
 
-fn delete_victory(
-    mut commands: Commands, 
-    mut query: Query<Entity, With<VictoryText>>,
+fn show_player(
     mut player_query: Query<&mut Visibility, With<Player>>,
 ) {
-    for text in query.iter_mut(){
-        commands.entity(text).despawn_recursive();
-    }
-
     for mut player in player_query.iter_mut(){
         player.is_visible = true;
     }
@@ -290,24 +543,34 @@ fn check_win(
         i += 1;
     }
     if i == 0 {
-        state.set(GameState::Victory).unwrap();
+        // runs after take_hits, a death in the same frame takes precedence
+        let _ = state.set(GameState::Victory);
     }
 }
 
-fn setup_intro(
+// Title screen over the starfield; the stars stay on for the run that follows.
+fn setup_title(
     mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut size: ResMut<Sizes>,
+    mut speed: ResMut<Speed>,
+    mut menu: ResMut<Menu>,
+    starfield: Res<Starfield>,
     asset_server: Res<AssetServer>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
     mut windows: ResMut<Windows>,
 ) {
-    commands.spawn(Camera2dBundle::default());
+    spawn_camera(&mut commands);
 
     let window = windows.get_primary_mut().unwrap();
-    let text_color = Color::Rgba {
-        red: 255.,
-        green: 255.,
-        blue: 255.,
-        alpha: 0.5,
-    };
+
+    // Stars
+    size.star = window.width()/1000.;
+    speed.star = window.width()/30.;
+    spawn_stars(&mut commands, &mut meshes, &mut materials, window, &starfield, size.star);
 
     commands
         .spawn(NodeBundle {
@@ -321,27 +584,42 @@ fn setup_intro(
         })
         .insert(RootNode)
         .with_children(|parent| {
-            parent.spawn(
-                TextBundle::from_section(
-                    "Press Space To Play!",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: window.width()/20.,
-                        color: text_color,
-                    }
-                )
-            )
-            .insert(IntroText);
+            // the starfield stays in full view behind the title
+            spawn_overlay(parent, locale.font(&asset_server), window, &theme, 0., "title", locale.text("title", &controls));
         });
+    menu.open(Page::Title);
 }
 
-fn delete_intro(
-    mut commands: Commands, 
-    mut query: Query<Entity, With<IntroText>>
+// Clears the finished run so the title screen and the next setup start from scratch.
+fn teardown_game(
+    mut commands: Commands,
+    mut score: ResMut<Score>,
+    mut time: ResMut<GameTime>,
+    mut speed: ResMut<Speed>,
+    mut min_id: ResMut<IDmin>,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    music_controller: Option<Res<MusicController>>,
+    query: Query<Entity, Or<(With<Player>, With<Pickup>, With<Orb>, With<Star>, With<RootNode>, With<MainCamera>, With<ScreenFlash>, With<GuideArrow>, With<Ghost>)>>,
 ) {
-    for text in query.iter_mut(){
-        commands.entity(text).despawn_recursive();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(music_controller) = music_controller {
+        if let Some(sink) = audio_sinks.get(&music_controller.0) {
+            sink.stop();
+        }
     }
+    score.goal = 0;
+    score.points = 0;
+    score.hunter = None;
+    combo.shared.reset();
+    time.value = 0.;
+    speed.orb = 0.;
+    min_id.value = -1;
+    min_id.last = -1;
+    *stats = RunStats::default();
 }
 
 fn setup(
@@ -352,8 +630,11 @@ fn setup(
     mut size: ResMut<Sizes>,
     mut speed: ResMut<Speed>,
     mut node_query: Query<Entity, With<RootNode>>,
-    score: Res<Score>,
+    mut score: ResMut<Score>,
     volume: Res<Volume>,
+    mut run: RunStart,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
     asset_server: Res<AssetServer>,
     audio: Res<Audio>,
     audio_sinks: Res<Assets<AudioSink>>,
@@ -361,292 +642,191 @@ fn setup(
     windows.primary_mut().set_cursor_visibility(false);
 
     let window = windows.get_primary_mut().unwrap();
-    let mut rng = rand::thread_rng();
+    let rng = &mut run.rng.stream(Stream::Layout, 0);
 
-    let text_color = Color::Rgba {
-        red: 255.,
-        green: 255.,
-        blue: 255.,
-        alpha: 0.5,
-    };
+    score.hunt_at = run.config.mode.hunt_at();
+    score.race = run.config.players == Players::Versus;
+    score.hunter = None;
 
     let music = asset_server.load("music/p.mp3");
     let handle = audio_sinks.get_handle(audio.play_with_settings(music, PlaybackSettings::LOOP.with_volume((volume.value as f32)/100.0)));
     commands.insert_resource(MusicController(handle));
 
     // UI
-
     for node in node_query.iter_mut(){
-        commands.entity(node)
-            .with_children(|parent| {
-                parent.spawn(
-                    TextBundle::from_section(
-                        score.value.to_string(),
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: window.width()/40.,
-                            color: text_color,
-                        },
-                    )
-                    .with_style(Style {
-                        position_type: PositionType::Absolute,
-                        position: UiRect {
-                            bottom: Val::Px(window.height()/64.),
-                            left: Val::Px(window.width()/16.),
-                            ..default()
-                        },
-                        ..default()
-                    }),
-                )
-                .insert(ScoreText);
-            })
-            .with_children(|parent| {
-                parent.spawn(
-                    TextBundle::from_section(
-                        "0.0",
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: window.width()/40.,
-                            color: text_color,
-                        },
-                    )
-                    .with_style(Style {
-                        position_type: PositionType::Absolute,
-                        position: UiRect {
-                            bottom: Val::Px(window.height()/64.),
-                            //left: Val::Px(window.width()/2.), // hardcoded :<
-                            ..default()
-                        },
-                        ..default()
-                    }),
-                )
-                .insert(TimeText);
-            })
-            .with_children(|parent| {
-                parent.spawn(
-                    TextBundle::from_section(
-                        volume.value.to_string() + "%",
-                        TextStyle {
-                            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: window.width()/40.,
-                            color: text_color,
-                        },
-                    )
-                    .with_style(Style {
-                        position_type: PositionType::Absolute,
-                        position: UiRect {
-                            bottom: Val::Px(window.height()/64.),
-                            right: Val::Px(window.width()/16.),
-                            ..default()
-                        },
-                        ..default()
-                    }),
-                )
-                .insert(VolumeText);
-            });
+        commands.entity(node).with_children(|parent| {
+            spawn_hud(parent, locale.font(&asset_server), window, &theme, &score, volume.value);
+        });
     }
 
-    // Pickups
     size.pickup = window.width() * 0.01;
+    size.player = window.width()/50.;
+    size.orb = window.width() * 0.1;
+    speed.player = window.width()/3.;
+    // a client's bodies come from the host's snapshots
+    if run.net.is_client() {
+        return;
+    }
 
-    for i in 0..10{
-        let x = rng.gen_range(size.pickup - window.width()/2. .. -size.pickup + window.width()/2.);
-        let y = rng.gen_range(size.pickup - window.height()/2. .. -size.pickup + window.height()/2.);
-
-        commands.spawn(MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(size.pickup * 0.5).into()).into(),
-            material: materials.add(ColorMaterial::from(Color::WHITE)),
-            transform: Transform::from_translation(Vec3::new(x, y, 1.)),
-            ..default()
-        })
-            .insert(Pickup)
-            .insert(PickID{number: i})
-            .insert(CollideType::Pickup)
-            .insert(Collider(size.pickup))
-        .with_children(|parent| {
-            parent.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size.pickup).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::BLUE)),
-                transform: Transform::from_translation(Vec3::new(0., 0., -0.5)),
-                ..default()
-            });
-        });
+    // Pickups
+    let p_x = 0.;//-window.width()/4.;
+    let players = run.config.players;
+    let starts: Vec<Vec3> = (0..players.count())
+        .map(|seat| start_position(seat, players, p_x, window))
+        .collect();
+
+    let (pickups, orbs) = lay_out(rng, window, &size, &run.modifiers, &starts);
+    for (i, position) in pickups.into_iter().enumerate() {
+        spawn_pickup(&mut commands, &mut meshes, &mut materials, &theme, size.pickup, i as i32, position);
     }
 
     // Orbs
-    size.orb = window.width() * 0.1;
-    speed.orb = window.width()/7.5;
+    speed.orb = window.width()/7.5 * run.config.orb_speed();
 
-    for i in 0..4{
-        let x = (window.width() + size.orb + i as f32 * size.orb * 2. + i as f32 * size.orb) - window.width()/2.;
-        let y = rng.gen_range(size.orb - window.height()/2. .. -size.orb + window.height()/2.);
+    for position in orbs {
+        spawn_orb(&mut commands, &mut meshes, &mut materials, &theme, size.orb, position);
+    }
 
-        commands.spawn(MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(size.orb * 0.95).into()).into(),
-            material: materials.add(ColorMaterial::from(Color::BLACK)),
-            transform: Transform::from_translation(Vec3::new(x, y, 1.)),
-            ..default()
-        })
-            .insert(Orb)
-            .insert(CollideType::Orb)
-            .insert(Collider(size.orb))
-        .with_children(|parent| {
-            parent.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size.orb).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
-                ..default()
-            })
-            .insert(OrbBorder);
-        });
+    // Players (should be drawn at the end)
+    for (seat, start) in starts.into_iter().enumerate() {
+        spawn_player(&mut commands, &mut meshes, &mut materials, &theme, size.player, Player::new(seat, players.lives()), start);
     }
+}
 
-    // Stars
-    size.star = window.width()/1000.;
-    speed.star = window.width()/2000.;
-    let stars = ((window.width()/window.height())*100.).floor();
-
-    for _i in 0 .. stars as u8{
-        let x = rng.gen_range(size.star - window.width()/2. .. -size.star + window.width()/2.);
-        let y = rng.gen_range(size.star - window.height()/2. .. -size.star + window.height()/2.);
-        commands.spawn(MaterialMesh2dBundle {
-            mesh: meshes.add(shape::Circle::new(size.star).into()).into(),
-            material: materials.add(ColorMaterial::from(Color::WHITE)),
-            transform: Transform::from_translation(Vec3::new(x, y, 0.)),
-            ..default()
-        })
-        .insert(Star);
+// The seeded part of a run: pickups clear of the players and each other, then the orbs' heights.
+// setup and reset_game both go through here, so a restart replays the layout the run began with.
+fn lay_out(rng: &mut impl Rng, window: &Window, size: &Sizes, modifiers: &RunModifiers, starts: &[Vec3]) -> (Vec<Vec3>, Vec<Vec3>) {
+    let mut blockers: Vec<Blocker> = starts.iter()
+        .map(|start| Blocker::new(*start, size.player))
+        .collect();
+    let (min, max) = screen_bounds(window, size.pickup);
+    let mut pickups = Vec::new();
+    for _ in 0..modifiers.pickup_count {
+        let spot = find_spot(rng, min, max, size.pickup, &blockers);
+        blockers.push(Blocker::new(spot.extend(0.), size.pickup));
+        pickups.push(spot.extend(1.));
     }
-    
-    // Player (should be drawn at the end)
-    let p_x = 0.;//-window.width()/4.;
-    let p_y = 0.;
-    size.player = window.width()/50.;
-    speed.player = window.width()/3.;
+
+    let (min, max) = screen_bounds(window, size.orb);
+    let orbs = (0..modifiers.orb_count)
+        .map(|i| {
+            let x = (window.width() + size.orb + i as f32 * size.orb * 2. + i as f32 * size.orb) - window.width()/2.;
+            Vec3::new(x, spot_in(rng, min, max).y, 1.)
+        })
+        .collect();
+    (pickups, orbs)
+}
+
+fn spawn_player(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    theme: &Theme,
+    radius: f32,
+    player: Player,
+    position: Vec3,
+) -> Entity {
+    let core = core_paint(player.seat);
     commands.spawn(MaterialMesh2dBundle {
-        mesh: meshes.add(shape::Circle::new(size.player).into()).into(),
-        material: materials.add(ColorMaterial::from(Color::WHITE)),
-        transform: Transform::from_translation(Vec3::new(p_x, p_y, 2.)),
+        mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+        material: materials.add(ColorMaterial::from(theme.player_rim)),
+        transform: Transform::from_translation(position),
         ..default()
     })
-        .insert(Player)
+        .insert(player)
+        .insert(Paint::PlayerRim)
         .insert(CollideType::Player)
-        .insert(Collider(size.player))
+        .insert(Collider(radius))
         .with_children(|parent| {
             parent.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size.player * 0.95).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::BLUE)),
+                mesh: meshes.add(shape::Circle::new(radius * 0.95).into()).into(),
+                material: materials.add(ColorMaterial::from(theme.paint(core, 0.))),
                 transform: Transform::from_translation(Vec3::new(0., 0., 3.)),
                 ..default()
-            });
-        });
+            })
+            .insert(core);
+        })
+        .id()
 }
 
-fn nearest_pick(
-    mut commands: Commands,
-    mut pick_q: Query<(Entity, &Transform, &PickID), With<Pickup>>,
-    player_q: Query<&Transform, With<Player>>,
-    mut min_id: ResMut<IDmin>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    size: Res<Sizes>,
-    mut dist: ResMut<Dist>
-) {
-    dist.value = f32::INFINITY;
-    let mut vs = Vec::new();
-    for player in player_q.iter(){
-        for (_entity, pick, pick_id) in pick_q.iter_mut() {
-            dist.value = player.translation.distance(pick.translation);
-            vs.push((dist.value, pick_id.number));
-        }
-    }
-    let mut minid = -1;
-    let mut minvalue = f32::INFINITY;
-    for v in vs {
-        let (a, b) = v;
-        if a < minvalue {
-            minvalue = a;
-            minid = b;
-        }
-    }
-    min_id.value = minid;
-
-    if min_id.value != min_id.last {
-        for (entity, _pick, pick_id) in pick_q.iter_mut() {
-            if pick_id.number == min_id.value {
-                commands.entity(entity).despawn_descendants();
-                    commands.entity(entity)
-                        .with_children(|parent| {
-                            parent.spawn(MaterialMesh2dBundle {
-                                mesh: meshes.add(shape::Circle::new(size.pickup).into()).into(),
-                                material: materials.add(ColorMaterial::from(Color::YELLOW)),
-                                transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
-                                ..default()
-                            });
-                        });
-            }
-            if pick_id.number == min_id.last {
-                commands.entity(entity).despawn_descendants();
-                    commands.entity(entity)
-                        .with_children(|parent| {
-                            parent.spawn(MaterialMesh2dBundle {
-                                mesh: meshes.add(shape::Circle::new(size.pickup).into()).into(),
-                                material: materials.add(ColorMaterial::from(Color::BLUE)),
-                                transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
-                                ..default()
-                            });
-                        });
-                
-            }
-        }
-    }
-    min_id.last = min_id.value;
+fn spawn_pickup(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    theme: &Theme,
+    radius: f32,
+    number: i32,
+    position: Vec3,
+) -> Entity {
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(shape::Circle::new(radius * 0.5).into()).into(),
+        material: materials.add(ColorMaterial::from(theme.pickup)),
+        transform: Transform::from_translation(position),
+        ..default()
+    })
+        .insert(Pickup)
+        .insert(Paint::Pickup)
+        .insert(PickID{number})
+        .insert(CollideType::Pickup)
+        .insert(Collider(radius))
+    .with_children(|parent| {
+        parent.spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+            material: materials.add(ColorMaterial::from(theme.pickup_ring)),
+            transform: Transform::from_translation(Vec3::new(0., 0., -0.5)),
+            ..default()
+        })
+        .insert(Paint::PickupRing)
+        .insert(TargetRing);
+    })
+    .id()
 }
 
-fn dead_text(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut windows: ResMut<Windows>,
-    mut query: Query<Entity, With<RootNode>>,
-) {
-    let window = windows.get_primary_mut().unwrap();
-    let text_color = Color::Rgba {
-        red: 255.,
-        green: 255.,
-        blue: 255.,
-        alpha: 0.5,
-    };
-
-    for node in query.iter_mut(){
-        commands.entity(node).with_children(|parent|{
-            parent.spawn(
-                TextBundle::from_section(
-                    "Defeat!\nPress Space To Play Again!",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                        font_size: window.width()/20.,
-                        color: text_color,
-                    },
-                )
-                .with_text_alignment(TextAlignment::TOP_CENTER)
-            )
-            .insert(DeadText);
-        });
-    }
+// What a run is laid out from, one parameter so reset_game stays under bevy's limit.
+#[derive(SystemParam)]
+struct RunStart<'w, 's> {
+    config: Res<'w, RunConfig>,
+    modifiers: Res<'w, RunModifiers>,
+    rng: ResMut<'w, GameRng>,
+    net: Res<'w, NetSession>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
 }
 
-fn delete_dead(
-    mut commands: Commands, 
-    mut query: Query<Entity, With<DeadText>>
-) {
-    for text in query.iter_mut(){
-        commands.entity(text).despawn_recursive();
-    }
+fn spawn_orb(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    theme: &Theme,
+    radius: f32,
+    position: Vec3,
+) -> Entity {
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(shape::Circle::new(radius * 0.95).into()).into(),
+        material: materials.add(ColorMaterial::from(theme.orb)),
+        transform: Transform::from_translation(position),
+        ..default()
+    })
+        .insert(Orb)
+        .insert(Paint::Orb)
+        .insert(CollideType::Orb)
+        .insert(Collider(radius))
+    .with_children(|parent| {
+        parent.spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(shape::Circle::new(radius).into()).into(),
+            material: materials.add(ColorMaterial::from(theme.orb_border(0.))),
+            transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
+            ..default()
+        })
+        .insert(OrbBorder)
+        .insert(Paint::OrbBorder);
+    })
+    .id()
 }
 
 fn reset_game(
     mut windows: ResMut<Windows>,
-    mut color: ResMut<OrbsRGB>,
+    theme: Res<Theme>,
     mut size: ResMut<Sizes>,
     mut speed: ResMut<Speed>,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -654,115 +834,84 @@ fn reset_game(
     mut commands: Commands,
     mut score: ResMut<Score>,
     mut time: ResMut<GameTime>,
-    mut player_query: Query<(Entity, &mut Transform), (With<Player>, Without<Orb>)>,
-    mut orb_query: Query<(Entity, &mut Transform), (With<Orb>, Without<Player>)>
+    mut player_query: Query<(Entity, &mut Transform, &mut Player, &mut Visibility), Without<Orb>>,
+    mut orb_query: Query<(Entity, &mut Transform), (With<Orb>, Without<Player>)>,
+    pickup_query: Query<Entity, With<Pickup>>,
+    mut run: RunStart,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
 ) {
-    let mut rng = rand::thread_rng();
     let window = windows.get_primary_mut().unwrap();
-
-    score.value = 0;
+    let rng = &mut run.rng.stream(Stream::Layout, 0);
+
+    score.goal = 0;
+    score.points = 0;
+    score.hunt_at = run.config.mode.hunt_at();
+    score.race = run.config.players == Players::Versus;
+    score.hunter = None;
+    combo.shared.reset();
     time.value = 0.;
-    color.r = 1.;
-    color.g = 0.;
-    color.b = 0.;
-    speed.orb = window.width()/8.;
+    speed.orb = window.width()/8. * run.config.orb_speed();
+    *stats = RunStats::default();
+    if run.net.is_client() {
+        return;
+    }
     
-    for (p, mut transform) in player_query.iter_mut(){
-        let p_x = -window.width()/4.;
-        let p_y = 0.;
-
-        transform.translation.x = p_x;
-        transform.translation.y = p_y;
+    // where setup starts the players, the pickups are laid out around them
+    let p_x = 0.;
+    for (p, mut transform, mut player, mut visibility) in player_query.iter_mut(){
+        let core = core_paint(player.seat);
+
+        transform.translation = start_position(player.seat, run.config.players, p_x, window);
+        *player = Player::new(player.seat, run.config.players.lives());
+        visibility.is_visible = true;
+        commands.entity(p).remove::<Down>();
         commands.entity(p).despawn_descendants();
         commands
             .entity(p)
             .with_children(|parent| {
                 parent.spawn(MaterialMesh2dBundle {
                     mesh: meshes.add(shape::Circle::new(size.player * 0.95).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::BLUE)),
+                    material: materials.add(ColorMaterial::from(theme.paint(core, 0.))),
                     transform: Transform::from_translation(Vec3::new(0.,0.,4.)),
                     ..default()
-                });
+                }).insert(core);
                 parent.spawn(MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::Circle::new(size.player * 0.0095 * score.value as f32).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::WHITE)),
+                    mesh: meshes.add(shape::Circle::new(size.player * 0.0095 * score.goal as f32).into()).into(),
+                    material: materials.add(ColorMaterial::from(theme.player_fill)),
                     transform: Transform::from_translation(Vec3::new(0.,0.,5.)),
                     ..default()
-                });
+                }).insert(Paint::PlayerFill);
             });
     }
-    let mut i = 0;
-    for (o, mut transform) in orb_query.iter_mut(){
-        let o_x = (window.width() + size.orb + i as f32 * size.orb * 2. + i as f32 * size.orb) - window.width()/2.;
-        let o_y = rng.gen_range(size.orb - window.height()/2. .. -size.orb + window.height()/2.);
-        transform.translation.x = o_x;
-        transform.translation.y = o_y;
-        i += 1;
-        commands.entity(o).despawn_descendants();
-        commands
-            .entity(o)
-            .with_children(|parent| {
-                parent.spawn(MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::Circle::new(size.orb).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::RED)),
-                    transform: Transform::from_translation(Vec3::new(0.,0.,-1.)),
-                    ..default()
-                }).insert(OrbBorder);
-            });
-    }
-    if i == 0 {
+    // a restart from the pause menu can come mid hunt, with orbs already gone or reinforcements in
+    if orb_query.is_empty() {
         size.orb = window.width() * 0.1;
+    }
+    size.pickup = window.width() * 0.01;
+    let players = run.config.players;
+    let starts: Vec<Vec3> = (0..players.count())
+        .map(|seat| start_position(seat, players, p_x, window))
+        .collect();
+    let (pickups, orbs) = lay_out(rng, window, &size, &run.modifiers, &starts);
+
+    // collected pickups were moved on, so the old ones go and the layout's come back
+    for entity in pickup_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (i, position) in pickups.into_iter().enumerate() {
+        spawn_pickup(&mut commands, &mut meshes, &mut materials, &theme, size.pickup, i as i32, position);
+    }
 
-        for i in 0..4{
-            let x = (window.width() + size.orb + i as f32 * size.orb * 2. + i as f32 * size.orb) - window.width()/2.;
-            let y = rng.gen_range(size.orb - window.height()/2. .. -size.orb + window.height()/2.);
-
-            commands.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size.orb * 0.95).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::BLACK)),
-                transform: Transform::from_translation(Vec3::new(x, y, 1.)),
-                ..default()
-            })
-                .insert(Orb)
-                .insert(CollideType::Orb)
-                .insert(Collider(size.orb))
-            .with_children(|parent| {
-                parent.spawn(MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::Circle::new(size.orb).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::RED)),
-                    transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
-                    ..default()
-                })
-                .insert(OrbBorder);
-            });
+    let mut orbs = orbs.into_iter();
+    for (entity, mut transform) in orb_query.iter_mut() {
+        match orbs.next() {
+            Some(position) => transform.translation = position,
+            None => commands.entity(entity).despawn_recursive(),
         }
-
-        size.pickup = window.width() * 0.01;
-
-        for i in 0..10{
-            let x = rng.gen_range(size.pickup - window.width()/2. .. -size.pickup + window.width()/2.);
-            let y = rng.gen_range(size.pickup - window.height()/2. .. -size.pickup + window.height()/2.);
-
-            commands.spawn(MaterialMesh2dBundle {
-                mesh: meshes.add(shape::Circle::new(size.pickup * 0.5).into()).into(),
-                material: materials.add(ColorMaterial::from(Color::WHITE)),
-                transform: Transform::from_translation(Vec3::new(x, y, 1.)),
-                ..default()
-            })
-                .insert(Pickup)
-                .insert(PickID{number: i})
-                .insert(CollideType::Pickup)
-                .insert(Collider(size.pickup))
-            .with_children(|parent| {
-                parent.spawn(MaterialMesh2dBundle {
-                    mesh: meshes.add(shape::Circle::new(size.pickup).into()).into(),
-                    material: materials.add(ColorMaterial::from(Color::BLUE)),
-                    transform: Transform::from_translation(Vec3::new(0., 0., -0.5)),
-                    ..default()
-                });
-            });
-        }
-
+    }
+    for position in orbs {
+        spawn_orb(&mut commands, &mut meshes, &mut materials, &theme, size.orb, position);
     }
 }
 
@@ -777,13 +926,19 @@ fn toggle_music(
 
 fn update_time(
     time: Res<Time>, 
+    config: Res<RunConfig>,
     mut time_counter: ResMut<GameTime>, 
     mut query: Query<&mut Text, With<TimeText>>
 ) {
     for mut text in &mut query {
         time_counter.value = time_counter.value + time.delta_seconds();
 
-        text.sections[0].value = format!("{:.1}", time_counter.value);
+        // timed modes count down what is left instead
+        let shown = match config.mode.time_limit() {
+            Some(limit) => (limit - time_counter.value).max(0.),
+            None => time_counter.value,
+        };
+        text.sections[0].value = format!("{:.1}", shown);
     }
 }
 
@@ -792,37 +947,32 @@ fn update_score(
     mut query: Query<&mut Text, With<ScoreText>>
 ) {
     for mut text in &mut query {
-        text.sections[0].value = score.value.to_string();
+        text.sections[0].value = score.points.to_string();
     }
 }
 
 fn toggle_state(
     input: Res<Input<KeyCode>>, 
+    controls: Res<Controls>,
     mut state: ResMut<State<GameState>>, 
 ) {
-    if input.just_pressed(KeyCode::Space) {
+    if input.just_pressed(controls.confirm) {
         match state.current() {
-            GameState::Init => {
-                state.set(GameState::Game).unwrap();
-            }
             GameState::Game => {
-                state.set(GameState::Pause).unwrap();
+                // a death or win queued this frame takes precedence
+                let _ = state.set(GameState::Pause);
             }
-            GameState::Pause => {
-                state.set(GameState::Game).unwrap();
-            }
-            GameState::Dead => {
-                state.set(GameState::Game).unwrap();
-            }
-            GameState::Victory => {
-                state.set(GameState::Game).unwrap();
+            GameState::Init | GameState::Pause | GameState::Dead | GameState::Victory | GameState::TimeUp => {
+                // the menus on these screens handle their own confirm
             }
         }
     }
 }
 
 fn detect_collisions(
-    collision_query: Query<(Entity, &Collider, &CollideType, &Transform)>,
+    collision_query: Query<(Entity, &Collider, &CollideType, &Transform), Without<Down>>,
+    score: Res<Score>,
+    mut stats: ResMut<RunStats>,
     mut event_writer: EventWriter<CollisionEvent>,
 ) {
     for (entity_a, collider_a, collide_type_a, transform_a) in collision_query.iter() {
@@ -839,17 +989,31 @@ fn detect_collisions(
                     } else {
                         event_writer.send(CollisionEvent(entity_b, entity_a));
                     }
+                } else if !score.hunting() {
+                    // near misses only count while orbs can still kill
+                    if let (CollideType::Player, CollideType::Orb) | (CollideType::Orb, CollideType::Player) = (collide_type_a, collide_type_b) {
+                        let player_radius = if collide_type_a == &CollideType::Player { collider_a.0 } else { collider_b.0 };
+                        stats.near_miss(distance.length() - collider_a.0 - collider_b.0, player_radius * 2.);
+                    }
                 }
             }
         }
     }
 }
 
+// Particle and camera feedback plus the telemetry, one parameter so manage_collisions stays under bevy's limit.
+#[derive(SystemParam)]
+struct Effects<'w, 's> {
+    particles: EventWriter<'w, 's, ParticleBurst>,
+    camera: EventWriter<'w, 's, CameraEvent>,
+    telemetry: EventWriter<'w, 's, TelemetryEvent>,
+}
+
 fn manage_collisions(
     mut score: ResMut<Score>,
     mut speed: ResMut<Speed>,
-    mut rgb: ResMut<OrbsRGB>,
-    mut state: ResMut<State<GameState>>, 
+    theme: Res<Theme>,
+    mut hit_writer: EventWriter<PlayerHit>,
     size: Res<Sizes>,
     windows: Res<Windows>,
     mut commands: Commands,
@@ -857,8 +1021,10 @@ fn manage_collisions(
     mut materials: ResMut<Assets<ColorMaterial>>,
     mut collision_event_reader: EventReader<CollisionEvent>,
     mut pickup_event_writer: EventWriter<PickupCollision>,
-    mut query: Query<(Entity, &CollideType)>,
-    mut o_query: Query<Entity, With<Orb>>,
+    mut effects: Effects,
+    mut stats: ResMut<RunStats>,
+    mut combo: ResMut<Combo>,
+    mut query: Query<(Entity, &CollideType, &mut Transform, Option<&mut Player>)>,
     mut pick_q: Query<Entity, With<Pickup>>
 ) {
     for event in collision_event_reader.iter() {
@@ -866,67 +1032,85 @@ fn manage_collisions(
             CollisionEvent(entity_a, entity_b) => {
                 let mut collide_a = None;
                 let mut collide_b = None;
-                for (entity, collide_type) in query.iter_mut() {
+                let mut pos_a = Vec3::ZERO;
+                let mut pos_b = Vec3::ZERO;
+                for (entity, collide_type, transform, _) in query.iter() {
                     if &entity == entity_a {
-                        collide_a = Some(collide_type);
+                        collide_a = Some(*collide_type);
+                        pos_a = transform.translation;
                     } else if &entity == entity_b {
-                        collide_b = Some(collide_type);
+                        collide_b = Some(*collide_type);
+                        pos_b = transform.translation;
                     }
                 }
                 match (collide_a, collide_b) {
                     (Some(CollideType::Player), Some(CollideType::Pickup)) => {
                         //player hit pickup
                         pickup_event_writer.send(PickupCollision(*entity_b));
-                        score.value += 1;
+                        effects.particles.send(ParticleBurst {
+                            position: pos_b,
+                            direction: (pos_b - pos_a).truncate(),
+                            scale: size.pickup,
+                            emitter: Emitter::sparks(theme.spark),
+                        });
+                        // versus rivals each build their own streak
+                        let gained = match query.get_mut(*entity_a) {
+                            Ok((_, _, _, Some(mut player))) => combo.pickup(&mut player.chain, score.race),
+                            _ => combo.pickup(&mut Chain::default(), false),
+                        };
+                        if !score.race {
+                            score.goal += 1;
+                        }
+                        score.points += gained;
+                        stats.pickups += 1;
+                        // in co-op the goal is shared and each player's fill shows their part of it,
+                        // in versus the goal is the leader's pickups and whoever gets there first hunts
+                        let (core, share, seat) = match query.get_mut(*entity_a) {
+                            Ok((_, _, _, Some(mut player))) => {
+                                player.pickups += 1;
+                                player.points += gained;
+                                if score.race {
+                                    score.goal = score.goal.max(player.pickups);
+                                    if score.goal_reached() {
+                                        score.hunter = Some(player.seat);
+                                    }
+                                }
+                                (core_paint(player.seat), score.share(player.pickups), player.seat)
+                            }
+                            _ => (Paint::PlayerCore, score.progress(), 0),
+                        };
+                        effects.telemetry.send(TelemetryEvent::Pickup { seat, x: pos_b.x, y: pos_b.y });
 
-                        if score.value <= 100 {
+                        if !score.hunting() || score.goal_reached() {
                             commands
                                 .entity(*entity_a)
                                 .with_children(|parent| {
                                     parent.spawn(MaterialMesh2dBundle {
                                         mesh: meshes.add(shape::Circle::new(size.player * 0.95).into()).into(),
-                                        material: materials.add(ColorMaterial::from(Color::BLUE)),
+                                        material: materials.add(ColorMaterial::from(theme.paint(core, 0.))),
                                         transform: Transform::from_translation(Vec3::new(0.,0.,4.)),
                                         ..default()
-                                    });
+                                    }).insert(core);
                                     parent.spawn(MaterialMesh2dBundle {
-                                        mesh: meshes.add(shape::Circle::new(size.player * score.value as f32 / 100. as f32).into()).into(),
-                                        material: materials.add(ColorMaterial::from(Color::WHITE)),
+                                        mesh: meshes.add(shape::Circle::new(size.player * share).into()).into(),
+                                        material: materials.add(ColorMaterial::from(theme.player_fill)),
                                         transform: Transform::from_translation(Vec3::new(0.,0.,5.)),
                                         ..default()
-                                    });
+                                    }).insert(Paint::PlayerFill);
                                 });
                         
                             let window = windows.get_primary().unwrap();
                             // window.width()/8.
-                            speed.orb += window.width()/700.;
+                            speed.orb += window.width()/700. * speed.ramp;
                             //println!("{}", speed.orb);
-                            rgb.r -= 0.01;
-                            rgb.b += 0.01;
-
-                            let new_rgb = Color::Rgba {
-                                red: rgb.r,
-                                green: rgb.g,
-                                blue: rgb.b,
-                                alpha: 1.0,
-                            };
-
-                            for o in o_query.iter_mut(){
-                                commands.entity(o).despawn_descendants();
-
-                                commands
-                                    .entity(o)
-                                    .with_children(|parent| {
-                                        parent.spawn(MaterialMesh2dBundle {
-                                            mesh: meshes.add(shape::Circle::new(size.orb).into()).into(),
-                                            material: materials.add(ColorMaterial::from(new_rgb)),
-                                            transform: Transform::from_translation(Vec3::new(0.,0.,-1.)),
-                                            ..default()
-                                        }).insert(OrbBorder);
-                                    });
-                            }
+                            // orb borders follow the score in apply_theme
                         }
-                        if score.value >= 100 {
+                        if score.goal_reached() {
+                            // goal reached, orbs are prey now
+                            effects.camera.send(CameraEvent::ZoomPulse);
+                            effects.camera.send(CameraEvent::Flash(theme.goal_flash));
+                        }
+                        if score.hunting() {
                             // delete all pickups
                             for pick in pick_q.iter_mut(){
                                 commands.entity(pick).despawn_recursive();
@@ -935,23 +1119,43 @@ fn manage_collisions(
                     },
                     (Some(CollideType::Player), Some(CollideType::Orb)) => {
                         //player hit orb
-                        if score.value < 100 {
-                            state.set(GameState::Dead).unwrap();
+                        let hunter = matches!(query.get(*entity_a), Ok((_, _, _, Some(player))) if score.can_hunt(player.seat));
+                        if !hunter {
+                            // lives, feedback and the end of the run are handled in take_hits
+                            hit_writer.send(PlayerHit(*entity_a, *entity_b));
                         } else {
+                            effects.particles.send(ParticleBurst {
+                                position: pos_b,
+                                direction: (pos_b - pos_a).truncate(),
+                                scale: size.orb,
+                                emitter: Emitter::shatter(theme.orb_border(score.progress())),
+                            });
+                            effects.camera.send(CameraEvent::Shake(0.4));
                             commands.entity(*entity_b).despawn_recursive();
+                            stats.orbs_destroyed += 1;
+                            effects.telemetry.send(TelemetryEvent::OrbDestroyed { x: pos_b.x, y: pos_b.y });
+                        }
+                    },
+                    (Some(CollideType::Player), Some(CollideType::Player)) => {
+                        //player hit player, only versus rivals shove each other
+                        if score.race {
+                            if let Ok([(_, _, mut transform_a, _), (_, _, mut transform_b, _)]) = query.get_many_mut([*entity_a, *entity_b]) {
+                                bump(&mut transform_a, &mut transform_b, size.player);
+                            }
                         }
                     },
                     (Some(CollideType::Pickup), Some(CollideType::Orb)) => {
                         //pickup hit orb
                         pickup_event_writer.send(PickupCollision(*entity_a));
+                        stats.pickups_lost += 1;
+                        effects.telemetry.send(TelemetryEvent::PickupLost { x: pos_a.x, y: pos_a.y });
                     },
                     (Some(CollideType::Pickup), Some(CollideType::Pickup)) => {
                         //pickup hit pickup
-                        //maybe relocate pickup
-                        
+                        pickup_event_writer.send(PickupCollision(*entity_b));
                     }
                     _ => {
-                        println!("unknown collision")
+                        warn!("unknown collision");
                     }
                 }
 
@@ -963,53 +1167,48 @@ fn manage_collisions(
 fn handle_pickup_collision(
     windows: Res<Windows>,
     size: Res<Sizes>,
+    mut rng: ResMut<GameRng>,
     mut event_reader: EventReader<PickupCollision>,
-    mut query: Query<(Entity, &mut Transform), (With<Pickup>, Without<Orb>)>,
+    mut query: Query<(Entity, &mut Transform, &PickID), (With<Pickup>, Without<Orb>)>,
+    blocker_query: Query<(&Transform, &Collider), Without<Pickup>>,
 ) {
     let window = windows.get_primary().unwrap();
-    let mut rng = rand::thread_rng();
+    let (min, max) = screen_bounds(window, size.pickup);
     for PickupCollision(event_entity) in event_reader.iter() {
-        for (query_entity, mut transform) in query.iter_mut() {
-            if event_entity == &query_entity {
-                transform.translation.x =
-                    rng.gen_range(
-                        size.pickup - window.width()/2. .. -size.pickup + window.width()/2.
-                    );
-                transform.translation.y =
-                    rng.gen_range(
-                        size.pickup - window.height()/2. .. -size.pickup + window.height()/2.
-                    );
+        // orbs, player and every other pickup, rebuilt per event so moved pickups count
+        let mut blockers = Vec::new();
+        for (transform, collider) in blocker_query.iter() {
+            blockers.push(Blocker::new(transform.translation, collider.0));
+        }
+        for (query_entity, transform, _) in query.iter() {
+            if event_entity != &query_entity {
+                blockers.push(Blocker::new(transform.translation, size.pickup));
             }
-         }
-    }
-}
+        }
 
-fn move_scene(
-    size: Res<Sizes>,
-    speed: Res<Speed>,
-    mut windows: ResMut<Windows>,
-    mut s_query: Query<&mut Transform, With<Star>>,
-) {
-    let window = windows.get_primary_mut().unwrap();
-    for mut transform in s_query.iter_mut() {
-        if transform.translation.x + size.star > -window.width()/2. {
-            transform.translation.x -= speed.star;
-        } else {
-            transform.translation.x = window.width()/2. + size.star;
+        if let Ok((_, mut transform, pick_id)) = query.get_mut(*event_entity) {
+            // each pickup moves through its own spots, whatever order they're taken in
+            let spot = find_spot(&mut rng.stream(Stream::Pickup, pick_id.number), min, max, size.pickup, &blockers);
+            transform.translation.x = spot.x;
+            transform.translation.y = spot.y;
         }
     }
 }
 
 fn audio_control(
     input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
     mut volume: ResMut<Volume>,
     audio_sinks: Res<Assets<AudioSink>>,
     music_controller: Local<Handle<AudioSink>>,
+    mut telemetry: EventWriter<TelemetryEvent>,
     mut query: Query<&mut Text, With<VolumeText>>
 ) {
-    if input.pressed(KeyCode::M) {
+    if input.pressed(controls.mute) {
         if let Some(sink) = audio_sinks.get(&*music_controller) {
-            println!("M pressed");
+            if volume.value != 0 {
+                telemetry.send(TelemetryEvent::Mute);
+            }
             volume.value = 0;
             sink.set_volume(0.0);
             for mut text in &mut query {
@@ -1026,26 +1225,39 @@ fn update_volume(
 
 fn movement(
     input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
     time: Res<Time>,
     size: Res<Sizes>,
     speed: Res<Speed>,
+    net: Res<NetSession>,
+    autopilot: Res<Autopilot>,
+    agent: Res<AgentEnv>,
+    mut stats: ResMut<RunStats>,
     mut windows: ResMut<Windows>,
-    mut query: Query<&mut Transform, With<Player>>,
+    mut query: Query<(&mut Transform, &Player), Without<Down>>,
 ) {
-    if let Ok(mut transform) = query.get_single_mut() {
-        let window = windows.get_primary_mut().unwrap();
+    let window = windows.get_primary_mut().unwrap();
+    for (mut transform, player) in query.iter_mut() {
         let mut direction = Vec3::ZERO;
-
-        if input.pressed(KeyCode::W) && transform.translation.y + size.player < window.height()/2.  {
+        let start = transform.translation;
+        // a joined client steers its seat from the other end, the bot steers the rest when it's on
+        let wanted = net.input_for(player.seat, time.elapsed_seconds())
+            .or_else(|| agent.input_for(player.seat))
+            .or_else(|| autopilot.input_for(player.seat))
+            .unwrap_or_else(|| controls.seat_direction(player.seat, &input, &gamepads, &buttons));
+
+        if wanted.y > 0. && transform.translation.y + size.player < window.height()/2.  {
             direction.y += 1.;
         }
-        if input.pressed(KeyCode::S) && transform.translation.y - size.player > -window.height()/2.  {
+        if wanted.y < 0. && transform.translation.y - size.player > -window.height()/2.  {
             direction.y -= 1.;
         }
-        if input.pressed(KeyCode::D) && transform.translation.x + size.player < window.width()/2.  {
+        if wanted.x > 0. && transform.translation.x + size.player < window.width()/2.  {
             direction.x += 1.;
         }
-        if input.pressed(KeyCode::A) && transform.translation.x - size.player > -window.width()/2. {
+        if wanted.x < 0. && transform.translation.x - size.player > -window.width()/2. {
             direction.x -= 1.;
         }
 
@@ -1063,6 +1275,7 @@ fn movement(
         if transform.translation.y > window.height()/2. - size.player {
             transform.translation.y = window.height()/2. - size.player;
         }
+        stats.distance += transform.translation.distance(start) / window.width();
     }
 }
 
@@ -1071,19 +1284,54 @@ fn orb_movement(
     speed: Res<Speed>,
     size: Res<Sizes>,
     windows: Res<Windows>,
+    modifiers: Res<RunModifiers>,
+    mut rng: ResMut<GameRng>,
+    mut stats: ResMut<RunStats>,
+    player_query: Query<&Transform, (With<Player>, Without<Orb>, Without<Down>)>,
     mut query: Query<&mut Transform, With<Orb>>,
 ) {
     let mut direction = Vec3::ZERO;
-    let mut rng = rand::thread_rng();
     let window = windows.get_primary().unwrap();
     direction.x = -1.;
+    stats.peak_orb_speed = stats.peak_orb_speed.max(speed.orb / window.width());
+
+    let mut blockers = Vec::new();
+    for transform in query.iter() {
+        blockers.push(Blocker::new(transform.translation, size.orb));
+    }
 
     for mut transform in query.iter_mut(){
         if transform.translation.x <= -window.width()/2. - size.orb{
-            transform.translation.x = window.width()/2. + size.orb;
-            transform.translation.y = rng.gen_range(size.orb - window.height()/2. .. -size.orb + window.height()/2.);
+            // re-enter at the right edge without landing on an orb that is still coming in
+            let x = window.width()/2. + size.orb;
+            let (min, max) = screen_bounds(window, size.orb);
+            let spot = find_spot(&mut rng.stream(Stream::OrbRespawn, 0), Vec2::new(x, min.y), Vec2::new(x, max.y), size.orb, &blockers);
+            transform.translation.x = spot.x;
+            transform.translation.y = spot.y;
+            // another orb coming back this frame has to keep clear of this one too
+            blockers.push(Blocker::new(transform.translation, size.orb));
         } else {
             transform.translation += speed.orb * time.delta_seconds() * direction.normalize_or_zero();
+
+            let step = speed.orb * time.delta_seconds();
+            // homing orbs lean toward whichever player is closest
+            let player_y = player_query.iter()
+                .map(|player| player.translation)
+                .min_by(|a, b| a.distance_squared(transform.translation).total_cmp(&b.distance_squared(transform.translation)))
+                .map(|player| player.y);
+            match (modifiers.orb_behavior, player_y) {
+                (OrbBehavior::Wave, _) => {
+                    // the phase comes from the position, so orbs need no state of their own
+                    transform.translation.y += step * 0.5 * (transform.translation.x / (window.width() * 0.1)).cos();
+                }
+                (OrbBehavior::Homing, Some(player_y)) => {
+                    let lean = step * 0.25;
+                    transform.translation.y += (player_y - transform.translation.y).clamp(-lean, lean);
+                }
+                _ => {}
+            }
+            let (min, max) = screen_bounds(window, size.orb);
+            transform.translation.y = transform.translation.y.clamp(min.y, max.y);
         }   
     }
 }
\ No newline at end of file
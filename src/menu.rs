@@ -0,0 +1,619 @@
+use bevy::{app::AppExit, ecs::schedule::ShouldRun, prelude::*};
+
+use crate::{
+    GameState, GameTime, RootNode, Score,
+    accessibility::{Accessibility, ColorVision},
+    camera::CameraSettings,
+    controls::Controls,
+    daily::{DailyChallenge, DailyHistory},
+    ghost::GhostSettings,
+    guidance::Guidance,
+    i18n::{Locale, Localized},
+    modes::{GameMode, Players, RunConfig},
+    net::{NetRole, NetSession},
+    scores::HighScores,
+    stats::RunStats,
+    theme::{TextPaint, Theme, ThemeLibrary},
+    versus::VersusResult,
+};
+
+// menu text height relative to the shorter window side
+const ITEM_RATIO: f32 = 1. / 18.;
+const TITLE_RATIO: f32 = 1. / 9.;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    Play,
+    Mode,
+    Difficulty,
+    Players,
+    HighScores,
+    Controls,
+    Quit,
+    Resume,
+    Restart,
+    PlayAgain,
+    Settings,
+    QuitToTitle,
+    Back,
+    Theme,
+    Language,
+    ColorVision,
+    HighContrast,
+    ShapeCues,
+    ReduceMotion,
+    Targeting,
+    Ghost,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Title,
+    Pause,
+    Settings,
+    HighScores,
+    Controls,
+    Results,
+}
+
+impl Page {
+    fn items(self) -> &'static [MenuAction] {
+        match self {
+            Page::Title => &[
+                MenuAction::Play,
+                MenuAction::Mode,
+                MenuAction::Difficulty,
+                MenuAction::Players,
+                MenuAction::HighScores,
+                MenuAction::Settings,
+                MenuAction::Controls,
+                MenuAction::Quit,
+            ],
+            Page::Pause => &[
+                MenuAction::Resume,
+                MenuAction::Restart,
+                MenuAction::Settings,
+                MenuAction::QuitToTitle,
+            ],
+            Page::Settings => &[
+                MenuAction::Theme,
+                MenuAction::Language,
+                MenuAction::ColorVision,
+                MenuAction::HighContrast,
+                MenuAction::ShapeCues,
+                MenuAction::ReduceMotion,
+                MenuAction::Targeting,
+                MenuAction::Ghost,
+                MenuAction::Back,
+            ],
+            Page::Results => &[
+                MenuAction::PlayAgain,
+                MenuAction::QuitToTitle,
+            ],
+            Page::HighScores | Page::Controls => &[MenuAction::Back],
+        }
+    }
+}
+
+// The page on screen plus the pages below it, so Back returns where Settings was opened.
+#[derive(Resource)]
+pub struct Menu {
+    page: Page,
+    selected: usize,
+    stack: Vec<(Page, usize)>,
+    dirty: bool,
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Menu {
+            page: Page::Title,
+            selected: 0,
+            stack: Vec::new(),
+            dirty: false,
+        }
+    }
+}
+
+impl Menu {
+    pub fn open(&mut self, page: Page) {
+        self.page = page;
+        self.selected = 0;
+        self.stack.clear();
+        self.dirty = true;
+    }
+
+    pub fn push(&mut self, page: Page) {
+        self.stack.push((self.page, self.selected));
+        self.page = page;
+        self.selected = 0;
+        self.dirty = true;
+    }
+
+    pub fn pop(&mut self) {
+        if let Some((page, selected)) = self.stack.pop() {
+            self.page = page;
+            self.selected = selected;
+            self.dirty = true;
+        }
+    }
+
+    fn items(&self) -> &'static [MenuAction] {
+        self.page.items()
+    }
+}
+
+// Fired when an item is chosen; each screen handles the actions it owns.
+pub struct MenuEvent(pub MenuAction);
+
+// Set by the pause menu, consumed by the run criteria in front of reset_game.
+#[derive(Resource, Default)]
+pub struct RestartRequest(pub bool);
+
+#[derive(Component)]
+pub struct MenuOverlay;
+#[derive(Component)]
+pub struct MenuList;
+#[derive(Component)]
+pub struct MenuItem(usize);
+// free text between the title and the items: run stats, score boards, key bindings
+#[derive(Component)]
+pub struct MenuInfo;
+
+fn item_size(window: &Window) -> f32 {
+    window.width().min(window.height()) * ITEM_RATIO
+}
+
+// Full-screen layer with a title and an empty item list, filled in by rebuild_menu.
+// `dim` is how much of the game behind it is covered.
+pub fn spawn_overlay(
+    parent: &mut ChildBuilder,
+    font: Handle<Font>,
+    window: &Window,
+    theme: &Theme,
+    dim: f32,
+    title: &'static str,
+    title_text: String,
+) -> Entity {
+    let mut background = theme.background;
+    background.set_a(dim);
+    let size = item_size(window);
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                // bevy's column direction runs bottom to top, reversed reads top to bottom
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: background.into(),
+            z_index: ZIndex::Global(50),
+            ..default()
+        })
+        .insert(MenuOverlay)
+        .with_children(|overlay| {
+            overlay.spawn(TextBundle::from_section(
+                title_text,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: window.width().min(window.height()) * TITLE_RATIO,
+                    color: theme.text,
+                },
+            ))
+            .insert(Localized(title))
+            .insert(TextPaint);
+
+            overlay.spawn(TextBundle::from_section(
+                "",
+                TextStyle {
+                    font,
+                    font_size: size * 0.75,
+                    color: theme.text,
+                },
+            )
+            .with_text_alignment(TextAlignment::TOP_CENTER)
+            .with_style(Style {
+                margin: UiRect::vertical(Val::Px(size * 0.5)),
+                ..default()
+            }))
+            .insert(MenuInfo)
+            .insert(TextPaint);
+
+            overlay.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::ColumnReverse,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(MenuList);
+        })
+        .id()
+}
+
+// Defeat or victory title over the run's numbers.
+pub fn spawn_results_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    state: Res<State<GameState>>,
+    config: Res<RunConfig>,
+    versus: Res<VersusResult>,
+    mut menu: ResMut<Menu>,
+    root_query: Query<Entity, With<RootNode>>,
+) {
+    let window = windows.get_primary().unwrap();
+    let title = match state.current() {
+        // whoever won, not how the run ended
+        _ if config.players == Players::Versus => versus.title(),
+        GameState::Victory => "victory",
+        GameState::TimeUp => "time_up",
+        _ => "defeat",
+    };
+    for root in root_query.iter() {
+        commands.entity(root).with_children(|parent| {
+            spawn_overlay(parent, locale.font(&asset_server), window, &theme, 0.5, title, locale.text(title, &controls));
+        });
+    }
+    menu.open(Page::Results);
+}
+
+pub fn spawn_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    mut menu: ResMut<Menu>,
+    root_query: Query<Entity, With<RootNode>>,
+) {
+    let window = windows.get_primary().unwrap();
+    for root in root_query.iter() {
+        commands.entity(root).with_children(|parent| {
+            spawn_overlay(parent, locale.font(&asset_server), window, &theme, 0.7, "paused", locale.text("paused", &controls));
+        });
+    }
+    menu.open(Page::Pause);
+}
+
+pub fn despawn_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<MenuOverlay>>,
+) {
+    for overlay in query.iter() {
+        commands.entity(overlay).despawn_recursive();
+    }
+}
+
+fn pad_pressed(gamepads: &Gamepads, buttons: &Input<GamepadButton>, button: GamepadButtonType) -> bool {
+    gamepads.iter().any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button)))
+}
+
+pub fn menu_navigation(
+    input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    controls: Res<Controls>,
+    mut menu: ResMut<Menu>,
+    mut events: EventWriter<MenuEvent>,
+) {
+    // a freshly opened page waits a frame, so the key that opened it doesn't also pick an item
+    if menu.dirty {
+        return;
+    }
+    let count = menu.items().len();
+    if input.any_just_pressed([controls.up, KeyCode::Up]) || pad_pressed(&gamepads, &buttons, GamepadButtonType::DPadUp) {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+    if input.any_just_pressed([controls.down, KeyCode::Down]) || pad_pressed(&gamepads, &buttons, GamepadButtonType::DPadDown) {
+        menu.selected = (menu.selected + 1) % count;
+    }
+    if input.any_just_pressed([controls.confirm, KeyCode::Return]) || pad_pressed(&gamepads, &buttons, GamepadButtonType::South) {
+        events.send(MenuEvent(menu.items()[menu.selected]));
+    } else if !menu.stack.is_empty()
+        && (input.just_pressed(KeyCode::Back) || pad_pressed(&gamepads, &buttons, GamepadButtonType::East))
+    {
+        events.send(MenuEvent(MenuAction::Back));
+    }
+}
+
+pub fn rebuild_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    theme: Res<Theme>,
+    locale: Res<Locale>,
+    mut menu: ResMut<Menu>,
+    list_query: Query<Entity, With<MenuList>>,
+) {
+    if !menu.dirty {
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+    let style = TextStyle {
+        font: locale.font(&asset_server),
+        font_size: item_size(window),
+        color: theme.text,
+    };
+
+    for list in list_query.iter() {
+        menu.dirty = false;
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|parent| {
+            for index in 0..menu.items().len() {
+                parent.spawn(TextBundle::from_section("", style.clone()))
+                    .insert(MenuItem(index));
+            }
+        });
+    }
+}
+
+fn on_off(locale: &Locale, controls: &Controls, value: bool) -> String {
+    locale.text(if value { "on" } else { "off" }, controls)
+}
+
+// Writes item labels with their current values and marks the selected one.
+pub fn update_menu_labels(
+    menu: Res<Menu>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    theme: Res<Theme>,
+    library: Res<ThemeLibrary>,
+    accessibility: Res<Accessibility>,
+    camera_settings: Res<CameraSettings>,
+    config: Res<RunConfig>,
+    guidance: Res<Guidance>,
+    ghost: Res<GhostSettings>,
+    mut item_query: Query<(&MenuItem, &mut Text)>,
+) {
+    for (MenuItem(index), mut text) in item_query.iter_mut() {
+        let action = match menu.items().get(*index) {
+            Some(action) => action,
+            None => continue,
+        };
+        let value = match action {
+            MenuAction::Mode => locale.text(config.mode.key(), &controls),
+            MenuAction::Difficulty => locale.text(config.difficulty.key(), &controls),
+            MenuAction::Players => locale.text(config.players.key(), &controls),
+            MenuAction::Theme => library.themes[library.index].name.clone(),
+            MenuAction::Language => locale.name().to_string(),
+            MenuAction::ColorVision => locale.text(match accessibility.color_vision {
+                ColorVision::Typical => "vision_typical",
+                ColorVision::Deuteranopia => "vision_deuteranopia",
+                ColorVision::Protanopia => "vision_protanopia",
+                ColorVision::Tritanopia => "vision_tritanopia",
+            }, &controls),
+            MenuAction::HighContrast => on_off(&locale, &controls, accessibility.high_contrast),
+            MenuAction::ShapeCues => on_off(&locale, &controls, accessibility.shape_cues),
+            MenuAction::ReduceMotion => on_off(&locale, &controls, camera_settings.reduce_motion),
+            MenuAction::Targeting => locale.text(guidance.rule.key(), &controls),
+            MenuAction::Ghost => on_off(&locale, &controls, ghost.enabled),
+            _ => String::new(),
+        };
+        let label = locale.format(label_key(*action), &controls, &[("value", value)]);
+
+        let mut color = theme.text;
+        if *index == menu.selected {
+            text.sections[0].value = format!("> {} <", label);
+            color.set_a(1.);
+        } else {
+            text.sections[0].value = label;
+        }
+        text.sections[0].style.color = color;
+    }
+}
+
+fn label_key(action: MenuAction) -> &'static str {
+    match action {
+        MenuAction::Play => "menu_play",
+        MenuAction::Mode => "menu_mode",
+        MenuAction::Difficulty => "menu_difficulty",
+        MenuAction::Players => "menu_players",
+        MenuAction::HighScores => "menu_high_scores",
+        MenuAction::Controls => "menu_controls",
+        MenuAction::Quit => "menu_quit",
+        MenuAction::Resume => "menu_resume",
+        MenuAction::Restart => "menu_restart",
+        MenuAction::PlayAgain => "menu_play_again",
+        MenuAction::Settings => "menu_settings",
+        MenuAction::QuitToTitle => "menu_quit_to_title",
+        MenuAction::Back => "menu_back",
+        MenuAction::Theme => "setting_theme",
+        MenuAction::Language => "setting_language",
+        MenuAction::ColorVision => "setting_color_vision",
+        MenuAction::HighContrast => "setting_high_contrast",
+        MenuAction::ShapeCues => "setting_shape_cues",
+        MenuAction::ReduceMotion => "setting_reduce_motion",
+        MenuAction::Targeting => "setting_targeting",
+        MenuAction::Ghost => "setting_ghost",
+    }
+}
+
+// Fills the info text for the page on screen.
+pub fn update_menu_info(
+    menu: Res<Menu>,
+    score: Res<Score>,
+    time: Res<GameTime>,
+    config: Res<RunConfig>,
+    scores: Res<HighScores>,
+    daily: Res<DailyChallenge>,
+    history: Res<DailyHistory>,
+    stats: Res<RunStats>,
+    versus: Res<VersusResult>,
+    net: Res<NetSession>,
+    clock: Res<Time>,
+    locale: Res<Locale>,
+    controls: Res<Controls>,
+    mut query: Query<&mut Text, With<MenuInfo>>,
+) {
+    let info = match menu.page {
+        Page::Pause => locale.format("pause_stats", &controls, &[
+            ("score", score.points.to_string()),
+            ("time", format!("{:.1}", time.value)),
+        ]),
+        Page::HighScores if config.mode == GameMode::Daily => {
+            let mut lines = vec![locale.text("daily_header", &controls)];
+            lines.extend(history.lines(&locale, &controls));
+            lines.join("\n")
+        }
+        Page::HighScores => {
+            let (header, entry) = match config.mode {
+                GameMode::Classic | GameMode::Daily => ("scores_header", "scores_entry"),
+                GameMode::TimeAttack | GameMode::Endless => ("scores_header_points", "scores_entry_points"),
+            };
+            let mut lines = vec![locale.format(header, &controls, &[
+                ("mode", locale.text(config.mode.key(), &controls)),
+                ("difficulty", locale.text(config.difficulty.key(), &controls)),
+            ])];
+            let board = scores.board(&config);
+            if board.is_empty() {
+                lines.push(locale.text("scores_empty", &controls));
+            }
+            for (rank, best) in board.iter().enumerate() {
+                lines.push(locale.format(entry, &controls, &[
+                    ("rank", (rank + 1).to_string()),
+                    ("time", format!("{:.1}", best)),
+                    ("points", format!("{:.0}", best)),
+                ]));
+            }
+            lines.join("\n")
+        }
+        Page::Controls => {
+            let mut lines: Vec<String> = controls.named().iter()
+                .map(|(name, key_code)| format!("{}: {}", locale.text(&format!("control_{}", name), &controls), locale.key_name(*key_code)))
+                .collect();
+            lines.push(locale.text("controls_pad", &controls));
+            lines.join("\n")
+        }
+        Page::Results if config.players == Players::Versus => {
+            format!("{}\n\n{}", stats.lines(time.value, score.points, &locale, &controls), versus.lines(&locale, &controls))
+        }
+        Page::Results if config.mode == GameMode::Daily => {
+            let note = if daily.official { "daily_official" } else { "daily_practice" };
+            format!("{}\n\n{}", stats.lines(time.value, score.points, &locale, &controls), locale.text(note, &controls))
+        }
+        Page::Results => stats.lines(time.value, score.points, &locale, &controls),
+        Page::Title => {
+            let mut lines = Vec::new();
+            if config.mode == GameMode::Daily {
+                lines.push(daily.describe(&history, &locale, &controls));
+            }
+            lines.extend(net.describe(clock.elapsed_seconds(), &locale, &controls));
+            lines.join("\n\n")
+        }
+        Page::Settings => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != info {
+            text.sections[0].value = info.clone();
+        }
+    }
+}
+
+// Settings and page changes work the same from every screen that shows a menu.
+pub fn settings_actions(
+    mut events: EventReader<MenuEvent>,
+    mut menu: ResMut<Menu>,
+    mut library: ResMut<ThemeLibrary>,
+    mut locale: ResMut<Locale>,
+    mut accessibility: ResMut<Accessibility>,
+    mut camera_settings: ResMut<CameraSettings>,
+    mut guidance: ResMut<Guidance>,
+    mut ghost: ResMut<GhostSettings>,
+) {
+    for MenuEvent(action) in events.iter() {
+        match action {
+            MenuAction::Settings => menu.push(Page::Settings),
+            MenuAction::Back => menu.pop(),
+            MenuAction::Theme => library.index = (library.index + 1) % library.themes.len(),
+            MenuAction::Language => locale.next(),
+            MenuAction::ColorVision => accessibility.color_vision = accessibility.color_vision.next(),
+            MenuAction::HighContrast => accessibility.high_contrast = !accessibility.high_contrast,
+            MenuAction::ShapeCues => accessibility.shape_cues = !accessibility.shape_cues,
+            MenuAction::ReduceMotion => camera_settings.reduce_motion = !camera_settings.reduce_motion,
+            MenuAction::Targeting => guidance.rule = guidance.rule.next(),
+            MenuAction::Ghost => ghost.enabled = !ghost.enabled,
+            _ => {}
+        }
+    }
+}
+
+pub fn title_actions(
+    mut events: EventReader<MenuEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut menu: ResMut<Menu>,
+    mut config: ResMut<RunConfig>,
+    net: Res<NetSession>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for MenuEvent(action) in events.iter() {
+        match action {
+            // a client starts when the host does
+            MenuAction::Play if net.is_client() => {}
+            MenuAction::Play => {
+                // the client drives the second seat, a solo run would leave it without a player
+                if net.role == NetRole::Host && config.players.count() < 2 {
+                    config.players = Players::Coop;
+                }
+                let _ = state.set(GameState::Game);
+            }
+            MenuAction::Mode => config.mode = config.mode.next(),
+            MenuAction::Difficulty => config.difficulty = config.difficulty.next(),
+            MenuAction::Players => {
+                config.players = config.players.next();
+                if net.role == NetRole::Host && config.players.count() < 2 {
+                    config.players = config.players.next();
+                }
+            }
+            MenuAction::HighScores => menu.push(Page::HighScores),
+            MenuAction::Controls => menu.push(Page::Controls),
+            MenuAction::Quit => exit.send(AppExit),
+            _ => {}
+        }
+    }
+}
+
+pub fn take_restart(mut restart: ResMut<RestartRequest>) -> ShouldRun {
+    if restart.0 {
+        restart.0 = false;
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Leaving a run, from the pause menu or the results screen.
+pub fn run_actions(
+    mut events: EventReader<MenuEvent>,
+    mut state: ResMut<State<GameState>>,
+    mut restart: ResMut<RestartRequest>,
+    net: Res<NetSession>,
+) {
+    for MenuEvent(action) in events.iter() {
+        match action {
+            // a change already queued this frame, say from the host, takes precedence
+            MenuAction::Resume => {
+                let _ = state.set(GameState::Game);
+            }
+            // the host restarts for everyone
+            MenuAction::Restart | MenuAction::PlayAgain if net.is_client() => {}
+            MenuAction::Restart | MenuAction::PlayAgain => {
+                // only flagged when the change goes through, or a later one would restart
+                restart.0 = state.set(GameState::Game).is_ok();
+            }
+            MenuAction::QuitToTitle => {
+                let _ = state.set(GameState::Init);
+            }
+            _ => {}
+        }
+    }
+}
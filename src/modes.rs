@@ -0,0 +1,333 @@
+use bevy::{prelude::*, utils::HashMap};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState, GameTime, Speed,
+    agent::AgentEnv,
+    autopilot::Autopilot,
+    daily::{DailyChallenge, DailyHistory},
+    net::NetSession,
+    telemetry::{Driver, TelemetryEvent},
+};
+
+const TIME_ATTACK_SECONDS: f32 = 60.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    // collect as much as possible before the clock runs out
+    TimeAttack,
+    // more and faster orbs the longer it goes, until the player is hit
+    Endless,
+    // a Classic run with the same layout and modifiers for everyone on a given day
+    Daily,
+}
+
+impl GameMode {
+    pub fn next(self) -> Self {
+        match self {
+            GameMode::Classic => GameMode::TimeAttack,
+            GameMode::TimeAttack => GameMode::Endless,
+            GameMode::Endless => GameMode::Daily,
+            GameMode::Daily => GameMode::Classic,
+        }
+    }
+
+    // string table key
+    pub fn key(self) -> &'static str {
+        match self {
+            GameMode::Classic => "mode_classic",
+            GameMode::TimeAttack => "mode_time_attack",
+            GameMode::Endless => "mode_endless",
+            GameMode::Daily => "mode_daily",
+        }
+    }
+
+    // pickups until the orbs can be hunted
+    pub fn hunt_at(self) -> Option<i32> {
+        match self {
+            GameMode::Classic | GameMode::Daily => Some(100),
+            GameMode::TimeAttack | GameMode::Endless => None,
+        }
+    }
+
+    pub fn time_limit(self) -> Option<f32> {
+        match self {
+            GameMode::Classic | GameMode::Endless | GameMode::Daily => None,
+            GameMode::TimeAttack => Some(TIME_ATTACK_SECONDS),
+        }
+    }
+
+    // the way a run ends that puts it on the leaderboard, daily runs keep their own history
+    pub fn ranked_end(self) -> Option<GameState> {
+        match self {
+            GameMode::Classic => Some(GameState::Victory),
+            GameMode::TimeAttack => Some(GameState::TimeUp),
+            GameMode::Endless => Some(GameState::Dead),
+            GameMode::Daily => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn next(self) -> Self {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "difficulty_easy",
+            Difficulty::Normal => "difficulty_normal",
+            Difficulty::Hard => "difficulty_hard",
+        }
+    }
+
+    // multiplier on the orbs' starting speed
+    pub fn orb_speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Players {
+    Solo,
+    // two players on one keyboard, sharing the goal
+    Coop,
+    // two players on one keyboard, racing each other to it
+    Versus,
+}
+
+impl Players {
+    pub fn next(self) -> Self {
+        match self {
+            Players::Solo => Players::Coop,
+            Players::Coop => Players::Versus,
+            Players::Versus => Players::Solo,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Players::Solo => "players_solo",
+            Players::Coop => "players_coop",
+            Players::Versus => "players_versus",
+        }
+    }
+
+    pub fn count(self) -> usize {
+        match self {
+            Players::Solo => 1,
+            Players::Coop | Players::Versus => 2,
+        }
+    }
+
+    // hits each player can take, the run ends when everyone is out, or all but one in versus
+    pub fn lives(self) -> u32 {
+        match self {
+            Players::Solo => 1,
+            Players::Coop | Players::Versus => 3,
+        }
+    }
+}
+
+// What the next run will be, picked on the title screen.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub mode: GameMode,
+    pub difficulty: Difficulty,
+    pub players: Players,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig {
+            mode: GameMode::Classic,
+            difficulty: Difficulty::Normal,
+            players: Players::Solo,
+        }
+    }
+}
+
+impl RunConfig {
+    // the daily challenge is the same for everyone, whatever difficulty they picked
+    pub fn orb_speed(&self) -> f32 {
+        match self.mode {
+            GameMode::Daily => Difficulty::Normal.orb_speed(),
+            _ => self.difficulty.orb_speed(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum OrbBehavior {
+    Straight,
+    // weave up and down on their way across
+    Wave,
+    // lean towards the player's height
+    Homing,
+}
+
+// The layout knobs a run starts from, only the daily challenge changes them.
+#[derive(Resource, Clone, Copy, Debug, Serialize)]
+pub struct RunModifiers {
+    pub orb_count: usize,
+    pub pickup_count: usize,
+    pub orb_behavior: OrbBehavior,
+    // multiplier on the orb speed-up per pickup
+    pub speed_ramp: f32,
+}
+
+impl Default for RunModifiers {
+    fn default() -> Self {
+        RunModifiers {
+            orb_count: 4,
+            pickup_count: 10,
+            orb_behavior: OrbBehavior::Straight,
+            speed_ramp: 1.,
+        }
+    }
+}
+
+// What a draw during a run is for, each has its own streams so one can't shift another.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream {
+    Layout,
+    // keyed by pickup number
+    Pickup,
+    OrbRespawn,
+    Reinforcement,
+}
+
+// Every random choice that shapes a run goes through this, cosmetics keep using thread_rng.
+// A draw only depends on the seed, its stream and key and how many draws that key made before,
+// so the same seed lays out the same run whatever order players pick things up in.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    draws: HashMap<(Stream, i32), u64>,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::seeded(rand::random())
+    }
+}
+
+impl GameRng {
+    pub fn seeded(seed: u64) -> Self {
+        GameRng { seed, draws: HashMap::default() }
+    }
+
+    // a generator for the next draw of one stream and key
+    pub fn stream(&mut self, stream: Stream, key: i32) -> ChaCha8Rng {
+        let count = self.draws.entry((stream, key)).or_insert(0);
+        let mixed = [stream as u64, key as u64, *count].iter()
+            .fold(self.seed, |mixed, part| mixed.rotate_left(21) ^ part.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        *count += 1;
+        ChaCha8Rng::seed_from_u64(mixed)
+    }
+}
+
+// A seed asked for from outside, the agent environment's resets set it.
+#[derive(Resource, Default)]
+pub struct FixedSeed(pub Option<u64>);
+
+// Runs before a run is laid out, on leaving the title and on restarts.
+pub fn prepare_run(
+    config: Res<RunConfig>,
+    fixed_seed: Res<FixedSeed>,
+    mut daily: ResMut<DailyChallenge>,
+    mut history: ResMut<DailyHistory>,
+    net: Res<NetSession>,
+    autopilot: Res<Autopilot>,
+    agent: Res<AgentEnv>,
+    mut modifiers: ResMut<RunModifiers>,
+    mut rng: ResMut<GameRng>,
+    mut speed: ResMut<Speed>,
+    windows: Res<Windows>,
+    mut telemetry: EventWriter<TelemetryEvent>,
+) {
+    let driver = if agent.active() {
+        Driver::Agent
+    } else if autopilot.driving() {
+        Driver::Bot
+    } else if net.is_client() {
+        Driver::Client
+    } else {
+        Driver::Human
+    };
+    let seed = if config.mode == GameMode::Daily {
+        // the game may have been left open past midnight
+        daily.refresh();
+        *modifiers = daily.modifiers;
+        info!("daily challenge {}: {:?}", daily.date, daily.modifiers);
+        // the host's history holds the day, bot and agent runs don't count
+        if !matches!(driver, Driver::Human) {
+            daily.official = false;
+        } else {
+            daily.claim(&mut history, config.players);
+        }
+        daily.seed
+    } else {
+        *modifiers = RunModifiers::default();
+        // drawn rather than left to from_entropy, so the telemetry can name it
+        fixed_seed.0.unwrap_or_else(rand::random)
+    };
+    *rng = GameRng::seeded(seed);
+    speed.ramp = modifiers.speed_ramp;
+
+    let window = windows.get_primary().unwrap();
+    telemetry.send(TelemetryEvent::RunStart {
+        seed,
+        driver,
+        config: *config,
+        modifiers: *modifiers,
+        width: window.width(),
+        height: window.height(),
+    });
+}
+
+pub fn check_time_limit(
+    config: Res<RunConfig>,
+    time: Res<GameTime>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(limit) = config.mode.time_limit() {
+        if time.value >= limit {
+            // runs after the collisions, a death in the same frame takes precedence
+            let _ = state.set(GameState::TimeUp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn streams_are_stable() {
+        // a daily layout comes from these draws, every build has to make the same ones
+        let mut rng = GameRng::seeded(42);
+        assert_eq!(rng.stream(Stream::Layout, 0).gen::<u64>(), 0x5596_F126_6D29_9133);
+        assert_eq!(rng.stream(Stream::Layout, 0).gen::<u64>(), 0xF803_24D9_CEAA_F3DF);
+        assert_eq!(rng.stream(Stream::Pickup, 3).gen::<u64>(), 0xB7A6_F819_3B86_0093);
+    }
+}
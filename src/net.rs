@@ -0,0 +1,493 @@
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    net::{SocketAddr, UdpSocket},
+};
+
+use crate::{
+    GameState, GameTime, Orb, PickID, Pickup, Player, Score, Sizes, spawn_orb, spawn_pickup, spawn_player,
+    controls::Controls,
+    coop::Down,
+    i18n::Locale,
+    menu::RestartRequest,
+    modes::RunConfig,
+    theme::Theme,
+};
+
+const DEFAULT_PORT: u16 = 7777;
+// seconds between snapshots, 20 a second
+const SNAPSHOT_INTERVAL: f32 = 0.05;
+// clients draw this far behind the newest snapshot so there is always a pair to blend between
+const INTERPOLATION_DELAY: f32 = 0.1;
+// seconds of silence before the other side counts as gone
+const TIMEOUT: f32 = 3.;
+const BUFFERED: usize = 32;
+// the client always plays the second seat
+const REMOTE_SEAT: usize = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NetRole {
+    Offline,
+    // runs the game and sends snapshots
+    Host,
+    // sends inputs and draws the host's snapshots
+    Client,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum BodyKind {
+    Player { seat: usize, lives: u32, points: i32, pickups: i32, down: bool },
+    Pickup(i32),
+    Orb,
+}
+
+// One entity on the host, positions in screen sizes like the ghost so any window size works.
+#[derive(Clone, Serialize, Deserialize)]
+struct Body {
+    id: u64,
+    kind: BodyKind,
+    x: f32,
+    y: f32,
+    visible: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Snapshot {
+    // counts runs on the host, a new number means a new run or a restart
+    run: u32,
+    // host clock, what clients interpolate on
+    at: f32,
+    state: GameState,
+    config: RunConfig,
+    time: f32,
+    goal: i32,
+    points: i32,
+    hunter: Option<usize>,
+    bodies: Vec<Body>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Packet {
+    // client to host every frame, also how the host learns where the client is
+    Input(f32, f32),
+    Snapshot(Snapshot),
+}
+
+// Tags entities drawn from snapshots with the host entity they stand for.
+#[derive(Component)]
+pub struct Mirror(u64);
+
+#[derive(Resource)]
+pub struct NetSession {
+    pub role: NetRole,
+    socket: Option<UdpSocket>,
+    address: String,
+    // the client on the host, the host on the client
+    peer: Option<SocketAddr>,
+    // when the peer was last heard from, local clock
+    heard: f32,
+    // host: runs started, client: the host run being shown
+    run: u32,
+    // host: the client's latest direction
+    remote: Vec2,
+    send_timer: f32,
+    // client: newest last, with the local time each arrived
+    snapshots: VecDeque<(f32, Snapshot)>,
+}
+
+impl NetSession {
+    fn offline() -> Self {
+        NetSession {
+            role: NetRole::Offline,
+            socket: None,
+            address: String::new(),
+            peer: None,
+            heard: f32::NEG_INFINITY,
+            run: 0,
+            remote: Vec2::ZERO,
+            send_timer: 0.,
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    // --host [address], --join <address>, --loopback keeps both ends on 127.0.0.1
+    pub fn from_args() -> Self {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let loopback = args.iter().any(|arg| arg == "--loopback");
+        let value = |flag: &str| {
+            let at = args.iter().position(|arg| arg == flag)?;
+            Some(args.get(at + 1).filter(|value| !value.starts_with("--")).cloned())
+        };
+        let local = if loopback { "127.0.0.1" } else { "0.0.0.0" };
+        let default = format!("{}:{}", local, DEFAULT_PORT);
+
+        let (role, bind, peer) = if let Some(address) = value("--host") {
+            (NetRole::Host, address.unwrap_or(default), None)
+        } else if let Some(address) = value("--join") {
+            let host = address.unwrap_or_else(|| format!("127.0.0.1:{}", DEFAULT_PORT));
+            (NetRole::Client, format!("{}:0", local), Some(host))
+        } else {
+            return NetSession::offline();
+        };
+
+        let socket = UdpSocket::bind(&bind).and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        });
+        let peer_address = peer.as_ref().map(|peer| peer.parse::<SocketAddr>());
+        match (socket, peer_address) {
+            (Ok(socket), None) => {
+                info!("hosting on {}", bind);
+                NetSession { role, socket: Some(socket), address: bind, ..NetSession::offline() }
+            }
+            (Ok(socket), Some(Ok(peer_address))) => {
+                info!("joining {}", peer_address);
+                NetSession {
+                    role,
+                    socket: Some(socket),
+                    address: peer_address.to_string(),
+                    peer: Some(peer_address),
+                    ..NetSession::offline()
+                }
+            }
+            (Err(e), _) => {
+                warn!("could not open {}: {}, playing offline", bind, e);
+                NetSession::offline()
+            }
+            (_, Some(Err(e))) => {
+                warn!("bad host address {}: {}, playing offline", peer.unwrap_or_default(), e);
+                NetSession::offline()
+            }
+        }
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.role == NetRole::Client
+    }
+
+    fn connected(&self, now: f32) -> bool {
+        self.peer.is_some() && now - self.heard < TIMEOUT
+    }
+
+    // the direction a seat is steered by from the other end, None for seats played here
+    pub fn input_for(&self, seat: usize, now: f32) -> Option<Vec2> {
+        (self.role == NetRole::Host && seat == REMOTE_SEAT && self.connected(now)).then_some(self.remote)
+    }
+
+    fn send(&self, packet: &Packet) {
+        let (socket, peer) = match (&self.socket, self.peer) {
+            (Some(socket), Some(peer)) => (socket, peer),
+            _ => return,
+        };
+        match ron::to_string(packet) {
+            Ok(text) => {
+                // a full buffer or a peer that went away, the next packet tries again
+                let _ = socket.send_to(text.as_bytes(), peer);
+            }
+            Err(e) => warn!("could not encode packet: {}", e),
+        }
+    }
+
+    // the title screen line, None when playing offline
+    pub fn describe(&self, now: f32, locale: &Locale, controls: &Controls) -> Option<String> {
+        let key = match (self.role, self.connected(now)) {
+            (NetRole::Offline, _) => return None,
+            (NetRole::Host, false) => "net_waiting",
+            (NetRole::Host, true) => "net_joined",
+            (NetRole::Client, false) => "net_connecting",
+            (NetRole::Client, true) => "net_connected",
+        };
+        let peer = self.peer.map(|peer| peer.to_string()).unwrap_or_default();
+        Some(locale.format(key, controls, &[("address", self.address.clone()), ("peer", peer)]))
+    }
+}
+
+// Piped after the game's own criteria, only the host or an offline game moves things and decides hits.
+pub fn authoritative(In(input): In<ShouldRun>, net: Res<NetSession>) -> ShouldRun {
+    if !net.is_client() {
+        return input;
+    }
+    match input {
+        ShouldRun::YesAndCheckAgain | ShouldRun::NoAndCheckAgain => ShouldRun::NoAndCheckAgain,
+        ShouldRun::Yes | ShouldRun::No => ShouldRun::No,
+    }
+}
+
+// On leaving the title and on restarts, so clients can tell a new run from the one they are showing.
+pub fn count_run(mut net: ResMut<NetSession>) {
+    if net.role == NetRole::Host {
+        net.run += 1;
+    }
+}
+
+pub fn receive_packets(time: Res<Time>, mut net: ResMut<NetSession>) {
+    let now = time.elapsed_seconds();
+    let mut buffer = [0; 65_536];
+    loop {
+        let received = match &net.socket {
+            Some(socket) => socket.recv_from(&mut buffer),
+            None => return,
+        };
+        let (length, from) = match received {
+            Ok(received) => received,
+            // WouldBlock once the queue is empty, anything else is dropped the same way
+            Err(_) => return,
+        };
+        let packet = std::str::from_utf8(&buffer[..length])
+            .map_err(|e| e.to_string())
+            .and_then(|text| ron::from_str::<Packet>(text).map_err(|e| e.to_string()));
+        let packet = match packet {
+            Ok(packet) => packet,
+            Err(e) => {
+                warn!("dropping packet from {}: {}", from, e);
+                continue;
+            }
+        };
+        match (net.role, packet) {
+            (NetRole::Host, Packet::Input(x, y)) => {
+                if net.peer != Some(from) {
+                    // the seat stays with whoever has it until they go quiet
+                    if net.connected(now) {
+                        continue;
+                    }
+                    info!("{} joined", from);
+                }
+                net.peer = Some(from);
+                net.heard = now;
+                net.remote = Vec2::new(x, y).clamp(Vec2::NEG_ONE, Vec2::ONE);
+            }
+            (NetRole::Client, Packet::Snapshot(snapshot)) if net.peer == Some(from) => {
+                net.heard = now;
+                // UDP can reorder, an older snapshot than the newest is no use
+                let newer = match net.snapshots.back() {
+                    Some((_, newest)) => (snapshot.run, snapshot.at) > (newest.run, newest.at),
+                    None => true,
+                };
+                if newer {
+                    net.snapshots.push_back((now, snapshot));
+                    if net.snapshots.len() > BUFFERED {
+                        net.snapshots.pop_front();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn send_input(
+    input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    state: Res<State<GameState>>,
+    net: Res<NetSession>,
+) {
+    if !net.is_client() {
+        return;
+    }
+    // sent on every screen as a keep-alive, only steers during a run
+    let direction = if *state.current() == GameState::Game {
+        controls.seat_direction(0, &input, &gamepads, &buttons)
+    } else {
+        Vec2::ZERO
+    };
+    net.send(&Packet::Input(direction.x, direction.y));
+}
+
+pub fn send_snapshot(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    config: Res<RunConfig>,
+    game_time: Res<GameTime>,
+    score: Res<Score>,
+    windows: Res<Windows>,
+    mut net: ResMut<NetSession>,
+    player_query: Query<(Entity, &Transform, &Player, &Visibility, Option<&Down>)>,
+    pickup_query: Query<(Entity, &Transform, &PickID), With<Pickup>>,
+    orb_query: Query<(Entity, &Transform), With<Orb>>,
+) {
+    if net.role != NetRole::Host || !net.connected(time.elapsed_seconds()) {
+        return;
+    }
+    net.send_timer -= time.delta_seconds();
+    if net.send_timer > 0. {
+        return;
+    }
+    net.send_timer = SNAPSHOT_INTERVAL;
+
+    let window = windows.get_primary().unwrap();
+    let body = |entity: Entity, transform: &Transform, kind, visible| Body {
+        id: entity.to_bits(),
+        kind,
+        x: transform.translation.x / window.width(),
+        y: transform.translation.y / window.height(),
+        visible,
+    };
+    let mut bodies = Vec::new();
+    for (entity, transform, player, visibility, down) in player_query.iter() {
+        bodies.push(body(entity, transform, BodyKind::Player {
+            seat: player.seat,
+            lives: player.lives,
+            points: player.points,
+            pickups: player.pickups,
+            down: down.is_some(),
+        }, visibility.is_visible));
+    }
+    for (entity, transform, pick_id) in pickup_query.iter() {
+        bodies.push(body(entity, transform, BodyKind::Pickup(pick_id.number), true));
+    }
+    for (entity, transform) in orb_query.iter() {
+        bodies.push(body(entity, transform, BodyKind::Orb, true));
+    }
+
+    net.send(&Packet::Snapshot(Snapshot {
+        run: net.run,
+        at: time.elapsed_seconds(),
+        state: state.current().clone(),
+        config: *config,
+        time: game_time.value,
+        goal: score.goal,
+        points: score.points,
+        hunter: score.hunter,
+        bodies,
+    }));
+}
+
+// Takes the client to whatever screen the host is on: new runs, restarts, results and the title.
+pub fn follow_host(
+    time: Res<Time>,
+    mut net: ResMut<NetSession>,
+    mut state: ResMut<State<GameState>>,
+    mut restart: ResMut<RestartRequest>,
+    mut config: ResMut<RunConfig>,
+) {
+    if !net.is_client() {
+        return;
+    }
+    let in_run = *state.current() != GameState::Init;
+    if !net.connected(time.elapsed_seconds()) {
+        if in_run && !net.snapshots.is_empty() {
+            warn!("lost the host");
+            net.snapshots.clear();
+            let _ = state.set(GameState::Init);
+        }
+        return;
+    }
+    let (run, host_state, host_config) = match net.snapshots.back() {
+        Some((_, newest)) => (newest.run, newest.state.clone(), newest.config),
+        None => return,
+    };
+    // the title shows what the host picked
+    if *config != host_config {
+        *config = host_config;
+    }
+
+    if host_state == GameState::Init {
+        if in_run {
+            let _ = state.set(GameState::Init);
+        }
+    } else if run != net.run {
+        net.run = run;
+        if in_run {
+            restart.0 = true;
+        }
+        if *state.current() != GameState::Game {
+            let _ = state.set(GameState::Game);
+        }
+    } else if matches!(host_state, GameState::Dead | GameState::Victory | GameState::TimeUp)
+        && matches!(state.current(), GameState::Game | GameState::Pause)
+    {
+        let _ = state.set(host_state);
+    }
+}
+
+// Draws the host's bodies a little in the past, blended between the two snapshots around that moment.
+pub fn apply_snapshot(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    size: Res<Sizes>,
+    windows: Res<Windows>,
+    net: Res<NetSession>,
+    state: Res<State<GameState>>,
+    mut score: ResMut<Score>,
+    mut game_time: ResMut<GameTime>,
+    mut mirror_query: Query<(Entity, &Mirror, &mut Transform, &mut Visibility, Option<&mut Player>)>,
+) {
+    if !net.is_client() || !matches!(state.current(), GameState::Game | GameState::Pause) {
+        return;
+    }
+    let (received, newest) = match net.snapshots.back() {
+        Some(newest) => newest,
+        None => return,
+    };
+    let render_at = newest.at + (time.elapsed_seconds() - received) - INTERPOLATION_DELAY;
+    // the last snapshot of this run at or before render_at, and the one after it
+    let run: Vec<&Snapshot> = net.snapshots.iter()
+        .map(|(_, snapshot)| snapshot)
+        .filter(|snapshot| snapshot.run == newest.run)
+        .collect();
+    let next = run.partition_point(|snapshot| snapshot.at <= render_at);
+    let (from, to) = match next {
+        0 => (run[0], run[0]),
+        next if next >= run.len() => (run[run.len() - 1], run[run.len() - 1]),
+        next => (run[next - 1], run[next]),
+    };
+    let blend = if to.at > from.at { ((render_at - from.at) / (to.at - from.at)).clamp(0., 1.) } else { 1. };
+
+    score.goal = to.goal;
+    score.points = to.points;
+    score.hunt_at = to.config.mode.hunt_at();
+    score.hunter = to.hunter;
+    game_time.value = from.time + (to.time - from.time) * blend;
+
+    let window = windows.get_primary().unwrap();
+    let scale = Vec2::new(window.width(), window.height());
+    let earlier: HashMap<u64, Vec2> = from.bodies.iter().map(|body| (body.id, Vec2::new(body.x, body.y))).collect();
+    let mut mirrors: HashMap<u64, Entity> = HashMap::new();
+
+    for (entity, Mirror(id), mut transform, mut visibility, player) in mirror_query.iter_mut() {
+        let body = match to.bodies.iter().find(|body| body.id == *id) {
+            Some(body) => body,
+            None => {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+        };
+        mirrors.insert(*id, entity);
+        let later = Vec2::new(body.x, body.y);
+        let position = earlier.get(id).map_or(later, |earlier| earlier.lerp(later, blend)) * scale;
+        transform.translation = position.extend(transform.translation.z);
+        visibility.is_visible = body.visible;
+
+        if let (BodyKind::Player { lives, points, pickups, down, .. }, Some(mut player)) = (body.kind, player) {
+            player.lives = lives;
+            player.points = points;
+            player.pickups = pickups;
+            if down {
+                commands.entity(entity).insert(Down);
+            } else {
+                commands.entity(entity).remove::<Down>();
+            }
+        }
+    }
+
+    for body in to.bodies.iter().filter(|body| !mirrors.contains_key(&body.id)) {
+        let position = (Vec2::new(body.x, body.y) * scale).extend(1.);
+        let entity = match body.kind {
+            BodyKind::Player { seat, lives, .. } => {
+                spawn_player(&mut commands, &mut meshes, &mut materials, &theme, size.player, Player::new(seat, lives), position.truncate().extend(2.))
+            }
+            BodyKind::Pickup(number) => {
+                spawn_pickup(&mut commands, &mut meshes, &mut materials, &theme, size.pickup, number, position)
+            }
+            BodyKind::Orb => spawn_orb(&mut commands, &mut meshes, &mut materials, &theme, size.orb, position),
+        };
+        commands.entity(entity).insert(Mirror(body.id));
+    }
+}
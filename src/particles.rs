@@ -0,0 +1,204 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::Rng;
+use std::f32::consts::{PI, TAU};
+
+use crate::{GameState, theme::lerp_color};
+
+// hard cap on particle entities, bursts beyond it are trimmed
+const MAX_PARTICLES: usize = 600;
+// drawn above the playfield but under the UI
+const PARTICLE_Z: f32 = 6.;
+
+// Describes one kind of burst. Speeds and sizes are multiples of the burst scale
+// so effects keep their look at any window size.
+pub struct Emitter {
+    count: usize,
+    speed: (f32, f32),
+    spread: f32,
+    drag: f32,
+    lifetime: (f32, f32),
+    size: (f32, f32),
+    color: (Color, Color),
+}
+
+impl Emitter {
+    // small quick sparks when the player grabs a pickup
+    pub fn sparks(color: Color) -> Self {
+        Emitter {
+            count: 14,
+            speed: (4., 12.),
+            spread: PI,
+            drag: 3.,
+            lifetime: (0.25, 0.5),
+            size: (0.35, 0.05),
+            color: (color, faded(color)),
+        }
+    }
+
+    // shards of an orb destroyed in the hunter phase
+    pub fn shatter(color: Color) -> Self {
+        Emitter {
+            count: 40,
+            speed: (0.5, 3.),
+            spread: TAU,
+            drag: 1.,
+            lifetime: (0.6, 1.2),
+            size: (0.12, 0.02),
+            color: (color, faded(color)),
+        }
+    }
+
+    // a quick flick off the player's side when an orb is grazed
+    pub fn graze(color: Color) -> Self {
+        Emitter {
+            count: 10,
+            speed: (3., 8.),
+            spread: PI / 3.,
+            drag: 4.,
+            lifetime: (0.2, 0.4),
+            size: (0.25, 0.05),
+            color: (color, faded(color)),
+        }
+    }
+
+    // the player blowing up
+    pub fn explosion(color: Color) -> Self {
+        Emitter {
+            count: 120,
+            speed: (1., 12.),
+            spread: TAU,
+            drag: 1.5,
+            lifetime: (0.8, 2.),
+            size: (0.5, 0.05),
+            color: (color, faded(color)),
+        }
+    }
+}
+
+fn faded(color: Color) -> Color {
+    let mut faded = color;
+    faded.set_a(0.);
+    faded
+}
+
+pub struct ParticleBurst {
+    pub position: Vec3,
+    // direction the spread is centered on, only matters for partial spreads
+    pub direction: Vec2,
+    // reference size in pixels the emitter values are multiplied with
+    pub scale: f32,
+    pub emitter: Emitter,
+}
+
+#[derive(Component)]
+pub struct Particle {
+    velocity: Vec2,
+    drag: f32,
+    age: f32,
+    lifetime: f32,
+    size: (f32, f32),
+    color: (Color, Color),
+}
+
+// Dead particles are hidden and kept here instead of despawned, so bursts
+// don't churn entities and materials.
+#[derive(Resource, Default)]
+pub struct ParticlePool {
+    mesh: Option<Mesh2dHandle>,
+    free: Vec<Entity>,
+    total: usize,
+}
+
+pub fn emit_particles(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut pool: ResMut<ParticlePool>,
+    mut events: EventReader<ParticleBurst>,
+    mut query: Query<(&mut Particle, &mut Transform, &mut Visibility, &Handle<ColorMaterial>)>,
+) {
+    let mut rng = rand::thread_rng();
+    let mesh = pool
+        .mesh
+        .get_or_insert_with(|| meshes.add(shape::Circle::new(1.).into()).into())
+        .clone();
+
+    for burst in events.iter() {
+        let emitter = &burst.emitter;
+        let heading = burst.direction.y.atan2(burst.direction.x);
+
+        for _ in 0..emitter.count {
+            let angle = heading + rng.gen_range(-0.5..0.5) * emitter.spread;
+            let particle = Particle {
+                velocity: Vec2::new(angle.cos(), angle.sin())
+                    * rng.gen_range(emitter.speed.0..emitter.speed.1)
+                    * burst.scale,
+                drag: emitter.drag,
+                age: 0.,
+                lifetime: rng.gen_range(emitter.lifetime.0..emitter.lifetime.1),
+                size: (emitter.size.0 * burst.scale, emitter.size.1 * burst.scale),
+                color: emitter.color,
+            };
+            let transform = Transform::from_translation(burst.position.truncate().extend(PARTICLE_Z))
+                .with_scale(Vec3::splat(particle.size.0));
+
+            if let Some(entity) = pool.free.pop() {
+                if let Ok((mut old, mut old_transform, mut visibility, handle)) = query.get_mut(entity) {
+                    if let Some(material) = materials.get_mut(handle) {
+                        material.color = particle.color.0;
+                    }
+                    *old = particle;
+                    *old_transform = transform;
+                    visibility.is_visible = true;
+                }
+            } else if pool.total < MAX_PARTICLES {
+                pool.total += 1;
+                commands.spawn(MaterialMesh2dBundle {
+                    mesh: mesh.clone(),
+                    material: materials.add(ColorMaterial::from(particle.color.0)),
+                    transform,
+                    ..default()
+                })
+                .insert(particle);
+            }
+        }
+    }
+}
+
+pub fn update_particles(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut pool: ResMut<ParticlePool>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Visibility, &Handle<ColorMaterial>)>,
+) {
+    if state.current() == &GameState::Pause {
+        return;
+    }
+    let dt = time.delta_seconds();
+
+    for (entity, mut particle, mut transform, mut visibility, handle) in query.iter_mut() {
+        if !visibility.is_visible {
+            continue;
+        }
+        particle.age += dt;
+        if particle.age >= particle.lifetime {
+            visibility.is_visible = false;
+            pool.free.push(entity);
+            continue;
+        }
+
+        let t = particle.age / particle.lifetime;
+        let drag = (1. - particle.drag * dt).max(0.);
+        particle.velocity *= drag;
+        transform.translation += (particle.velocity * dt).extend(0.);
+        transform.scale = Vec3::splat(particle.size.0 + (particle.size.1 - particle.size.0) * t);
+
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = lerp_color(particle.color.0, particle.color.1, t);
+        }
+    }
+}
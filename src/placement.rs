@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+// how many random candidates are tried before giving up on a clean spot
+const ATTEMPTS: u32 = 32;
+
+// Something a new spawn has to keep away from: center and its own body radius, room adds the spawn's.
+pub struct Blocker {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+impl Blocker {
+    pub fn new(position: Vec3, radius: f32) -> Self {
+        Blocker {
+            position: position.truncate(),
+            radius,
+        }
+    }
+}
+
+// Rejection sampling inside the [min, max] box: returns the first candidate that keeps
+// `radius` of room around itself from every blocker. When the field is too crowded
+// the candidate with the most room is used instead, so a spawn never fails.
+// Every candidate is drawn up front in 0..1 and scaled to the box after, so the draws
+// a spawn takes don't depend on the screen or on which candidate fits.
+pub fn find_spot(
+    rng: &mut impl Rng,
+    min: Vec2,
+    max: Vec2,
+    radius: f32,
+    blockers: &[Blocker],
+) -> Vec2 {
+    let candidates: Vec<Vec2> = (0..=ATTEMPTS).map(|_| spot_in(rng, min, max)).collect();
+    let mut best = candidates[0];
+    let mut best_room = room(best, radius, blockers);
+
+    for &candidate in &candidates[1..] {
+        if best_room >= 0. {
+            break;
+        }
+        let candidate_room = room(candidate, radius, blockers);
+        if candidate_room > best_room {
+            best = candidate;
+            best_room = candidate_room;
+        }
+    }
+    best
+}
+
+// A uniform spot in the box, drawn in 0..1 then scaled, a pinned axis stays on its one value.
+pub fn spot_in(rng: &mut impl Rng, min: Vec2, max: Vec2) -> Vec2 {
+    let unit = Vec2::new(rng.gen(), rng.gen());
+    min + (max - min) * unit
+}
+
+// Box a spawn of the given radius has to stay in to be fully on screen.
+pub fn screen_bounds(window: &Window, radius: f32) -> (Vec2, Vec2) {
+    (
+        Vec2::new(radius - window.width()/2., radius - window.height()/2.),
+        Vec2::new(-radius + window.width()/2., -radius + window.height()/2.),
+    )
+}
+
+// smallest gap between the candidate circle and any blocker, negative when overlapping
+fn room(candidate: Vec2, radius: f32, blockers: &[Blocker]) -> f32 {
+    let mut room = f32::INFINITY;
+    for blocker in blockers {
+        let gap = candidate.distance(blocker.position) - blocker.radius - radius;
+        if gap < room {
+            room = gap;
+        }
+    }
+    room
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn inside(spot: Vec2, min: Vec2, max: Vec2) -> bool {
+        spot.cmpge(min).all() && spot.cmple(max).all()
+    }
+
+    #[test]
+    fn keeps_clear_of_blockers() {
+        let (min, max) = (Vec2::new(-400., -300.), Vec2::new(400., 300.));
+        let blockers = [
+            Blocker::new(Vec3::ZERO, 100.),
+            Blocker::new(Vec3::new(-250., 150., 0.), 60.),
+            Blocker::new(Vec3::new(250., -150., 0.), 60.),
+        ];
+        for seed in 0..200 {
+            let spot = find_spot(&mut ChaCha8Rng::seed_from_u64(seed), min, max, 20., &blockers);
+            assert!(inside(spot, min, max), "seed {}: {} out of bounds", seed, spot);
+            assert!(room(spot, 20., &blockers) >= 0., "seed {}: {} overlaps a blocker", seed, spot);
+        }
+    }
+
+    #[test]
+    fn crowded_fallback_stays_in_bounds() {
+        let (min, max) = (Vec2::new(-100., -50.), Vec2::new(100., 50.));
+        // nowhere in the box has room, the least bad spot is still in it
+        let blockers = [Blocker::new(Vec3::ZERO, 500.)];
+        for seed in 0..200 {
+            let spot = find_spot(&mut ChaCha8Rng::seed_from_u64(seed), min, max, 20., &blockers);
+            assert!(inside(spot, min, max), "seed {}: {} out of bounds", seed, spot);
+        }
+    }
+
+    #[test]
+    fn room_counts_each_radius_once() {
+        let blockers = [Blocker::new(Vec3::ZERO, 10.)];
+        assert_eq!(room(Vec2::new(30., 0.), 20., &blockers), 0.);
+        assert_eq!(room(Vec2::new(40., 0.), 20., &blockers), 10.);
+    }
+
+    #[test]
+    fn same_draws_same_spot() {
+        let (min, max) = (Vec2::new(-400., -300.), Vec2::new(400., 300.));
+        let blockers = [Blocker::new(Vec3::ZERO, 100.)];
+        let first = find_spot(&mut ChaCha8Rng::seed_from_u64(7), min, max, 20., &blockers);
+        let second = find_spot(&mut ChaCha8Rng::seed_from_u64(7), min, max, 20., &blockers);
+        assert_eq!(first, second);
+    }
+}
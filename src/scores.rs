@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    GameState, GameTime, Score,
+    agent::AgentEnv,
+    autopilot::Autopilot,
+    daily::DailyChallenge,
+    ghost::{GhostLibrary, GhostRecorder},
+    modes::{GameMode, Players, RunConfig},
+    net::NetSession,
+    storage::{load_ron, save_ron},
+};
+
+// written next to the executable's working directory, not into assets/
+const SCORES_PATH: &str = "scores.ron";
+const BOARD_SIZE: usize = 10;
+
+// One board per mode and difficulty: fastest victories for Classic, most points for the others.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    boards: HashMap<String, Vec<f32>>,
+}
+
+pub fn board_key(config: &RunConfig) -> String {
+    // co-op runs get boards of their own, solo keys stay as they were
+    match config.players {
+        Players::Solo => format!("{:?}/{:?}", config.mode, config.difficulty),
+        players => format!("{:?}/{:?}/{:?}", config.mode, config.difficulty, players),
+    }
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        load_ron(SCORES_PATH)
+    }
+
+    pub fn save(&self) {
+        save_ron(SCORES_PATH, self);
+    }
+
+    pub fn board(&self, config: &RunConfig) -> &[f32] {
+        self.boards.get(&board_key(config)).map_or(&[], |board| board.as_slice())
+    }
+
+    // returns the place the result took on the board, if it made it
+    pub fn submit(&mut self, config: &RunConfig, result: f32) -> Option<usize> {
+        let lower_is_better = config.mode == GameMode::Classic;
+        let board = self.boards.entry(board_key(config)).or_default();
+        let rank = board.iter()
+            .position(|&best| if lower_is_better { result < best } else { result > best })
+            .unwrap_or(board.len());
+        if rank >= BOARD_SIZE {
+            return None;
+        }
+        board.insert(rank, result);
+        board.truncate(BOARD_SIZE);
+        Some(rank)
+    }
+}
+
+// Runs on every results screen, only the end the mode ranks makes it to the board.
+pub fn record_run(
+    state: Res<State<GameState>>,
+    time: Res<GameTime>,
+    score: Res<Score>,
+    config: Res<RunConfig>,
+    recorder: Res<GhostRecorder>,
+    daily: Res<DailyChallenge>,
+    mut scores: ResMut<HighScores>,
+    mut ghosts: ResMut<GhostLibrary>,
+    net: Res<NetSession>,
+    autopilot: Res<Autopilot>,
+    agent: Res<AgentEnv>,
+) {
+    // versus is head to head, nothing to rank against, a client only watched the host's numbers and neither bots nor agents are anyone
+    if config.players == Players::Versus || net.is_client() || autopilot.driving() || agent.active() || config.mode.ranked_end().as_ref() != Some(state.current()) {
+        return;
+    }
+    let result = match config.mode {
+        GameMode::Classic | GameMode::Daily => time.value,
+        GameMode::TimeAttack | GameMode::Endless => score.points as f32,
+    };
+    if let Some(rank) = scores.submit(&config, result) {
+        info!("new best #{}: {:.1}", rank + 1, result);
+        scores.save();
+        // the best run on the board is the one the ghost replays
+        if rank == 0 {
+            ghosts.store(&config, &daily, recorder.run.clone());
+            ghosts.save();
+        }
+    }
+}
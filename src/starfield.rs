@@ -0,0 +1,123 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use rand::Rng;
+use std::f32::consts::TAU;
+
+use crate::{Sizes, Speed, theme::Theme};
+
+#[derive(Component)]
+pub struct Star {
+    layer: usize,
+    brightness: f32,
+    phase: f32,
+    twinkle: f32,
+}
+
+// One depth layer of the background: far layers are slower, smaller and dimmer.
+pub struct StarLayer {
+    pub speed: f32,
+    pub size: f32,
+    pub brightness: f32,
+}
+
+#[derive(Resource)]
+pub struct Starfield {
+    // stars per layer for every million pixels of screen, so the look doesn't depend on aspect ratio
+    pub density: f32,
+    pub layers: Vec<StarLayer>,
+}
+
+impl Default for Starfield {
+    fn default() -> Self {
+        Starfield {
+            density: 30.,
+            layers: vec![
+                StarLayer { speed: 0.25, size: 0.6, brightness: 0.35 },
+                StarLayer { speed: 0.5, size: 0.9, brightness: 0.6 },
+                StarLayer { speed: 1., size: 1.3, brightness: 0.9 },
+            ],
+        }
+    }
+}
+
+pub fn spawn_stars(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    window: &Window,
+    starfield: &Starfield,
+    star_size: f32,
+) {
+    let mut rng = rand::thread_rng();
+    let mesh: Mesh2dHandle = meshes.add(shape::Circle::new(star_size).into()).into();
+    let count = (starfield.density * window.width() * window.height() / 1_000_000.).round() as usize;
+
+    for (layer_index, layer) in starfield.layers.iter().enumerate() {
+        for _ in 0..count {
+            let x = rng.gen_range(-window.width()/2. .. window.width()/2.);
+            let y = rng.gen_range(-window.height()/2. .. window.height()/2.);
+            let brightness = layer.brightness * rng.gen_range(0.6..1.);
+            let scale = layer.size * rng.gen_range(0.7..1.3);
+
+            commands.spawn(MaterialMesh2dBundle {
+                mesh: mesh.clone(),
+                material: materials.add(ColorMaterial::from(Color::rgba(1., 1., 1., brightness))),
+                transform: Transform::from_translation(Vec3::new(x, y, layer_index as f32 * 0.1))
+                    .with_scale(Vec3::splat(scale)),
+                ..default()
+            })
+            .insert(Star {
+                layer: layer_index,
+                brightness,
+                phase: rng.gen_range(0. .. TAU),
+                twinkle: rng.gen_range(0.5..3.),
+            });
+        }
+    }
+}
+
+pub fn move_scene(
+    time: Res<Time>,
+    size: Res<Sizes>,
+    speed: Res<Speed>,
+    starfield: Res<Starfield>,
+    windows: Res<Windows>,
+    mut s_query: Query<(&Star, &mut Transform)>,
+) {
+    let window = windows.get_primary().unwrap();
+    let mut rng = rand::thread_rng();
+
+    // stars speed up together with the orbs so the background sells the acceleration
+    let base_orb = window.width()/8.;
+    let boost = if speed.orb > 0. { speed.orb / base_orb } else { 1. };
+
+    for (star, mut transform) in s_query.iter_mut() {
+        let radius = size.star * transform.scale.x;
+        let layer_speed = starfield.layers.get(star.layer).map_or(1., |layer| layer.speed);
+
+        if transform.translation.x + radius > -window.width()/2. {
+            transform.translation.x -= speed.star * layer_speed * boost * time.delta_seconds();
+        } else {
+            transform.translation.x = window.width()/2. + radius;
+            transform.translation.y = rng.gen_range(-window.height()/2. .. window.height()/2.);
+        }
+    }
+}
+
+pub fn twinkle_stars(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    query: Query<(&Star, &Handle<ColorMaterial>)>,
+) {
+    let t = time.elapsed_seconds();
+    for (star, handle) in query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            let flicker = 0.75 + 0.25 * (t * star.twinkle + star.phase).sin();
+            material.color = theme.star;
+            material.color.set_a(theme.star.a() * star.brightness * flicker);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use crate::{controls::Controls, i18n::Locale};
+
+// Numbers for the results screen, filled in by the gameplay systems as the run goes.
+#[derive(Resource)]
+pub struct RunStats {
+    pub pickups: u32,
+    pub orbs_destroyed: u32,
+    // pickups an orb flew through, they get moved like after a pickup
+    pub pickups_lost: u32,
+    // orbs grazed without touching
+    pub near_misses: u32,
+    // in screen widths, so it reads the same on every resolution
+    pub distance: f32,
+    // smallest gap to a dangerous orb, in player widths
+    pub closest_call: f32,
+    // in screen widths per second
+    pub peak_orb_speed: f32,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        RunStats {
+            pickups: 0,
+            orbs_destroyed: 0,
+            pickups_lost: 0,
+            near_misses: 0,
+            distance: 0.,
+            closest_call: f32::INFINITY,
+            peak_orb_speed: 0.,
+        }
+    }
+}
+
+impl RunStats {
+    pub fn near_miss(&mut self, gap: f32, player_width: f32) {
+        self.closest_call = self.closest_call.min(gap / player_width);
+    }
+
+    pub fn lines(&self, time: f32, points: i32, locale: &Locale, controls: &Controls) -> String {
+        let closest_call = if self.closest_call.is_finite() {
+            format!("{:.2}", self.closest_call)
+        } else {
+            "-".to_string()
+        };
+        [
+            ("stats_points", points.to_string()),
+            ("stats_time", format!("{:.1}", time)),
+            ("stats_pickups", self.pickups.to_string()),
+            ("stats_orbs_destroyed", self.orbs_destroyed.to_string()),
+            ("stats_pickups_lost", self.pickups_lost.to_string()),
+            ("stats_near_misses", self.near_misses.to_string()),
+            ("stats_distance", format!("{:.1}", self.distance)),
+            ("stats_closest_call", closest_call),
+            ("stats_peak_orb_speed", format!("{:.2}", self.peak_orb_speed)),
+        ]
+        .into_iter()
+        .map(|(key, value)| locale.format(key, controls, &[("value", value)]))
+        .collect::<Vec<_>>()
+        .join("\n")
+    }
+}
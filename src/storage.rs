@@ -0,0 +1,24 @@
+use bevy::prelude::*;
+use serde::{Serialize, de::DeserializeOwned};
+use std::fs;
+
+// A file the game keeps next to where it runs, a missing or unreadable one starts over empty.
+pub fn load_ron<T: DeserializeOwned + Default>(path: &str) -> T {
+    match fs::read_to_string(path) {
+        Ok(text) => ron::from_str(&text).unwrap_or_else(|e| {
+            warn!("ignoring {}: {}", path, e);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+// Failing to save only costs the record, the game carries on.
+pub fn save_ron<T: Serialize>(path: &str, value: &T) {
+    let result = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())
+        .and_then(|text| fs::write(path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("could not save {}: {}", path, e);
+    }
+}
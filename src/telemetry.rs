@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    GameState, GameTime, Player, Score,
+    coop::Down,
+    modes::{RunConfig, RunModifiers},
+};
+
+// appended to next to scores.ron, one JSON object per line
+const TELEMETRY_PATH: &str = "telemetry.jsonl";
+// seconds of play between player position samples, what occupancy heatmaps are built from
+const SAMPLE_INTERVAL: f32 = 0.25;
+
+// Who was at the controls of a run, so bot and agent runs can be kept out of the picture of real play.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Driver {
+    Human,
+    // the autopilot, in demos, soaks or with --autopilot
+    Bot,
+    Agent,
+    // a networked client, whose seat is played remotely
+    Client,
+}
+
+// Something worth looking at after the fact. Positions are in world units, run_start gives the screen they're on.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TelemetryEvent {
+    RunStart {
+        seed: u64,
+        driver: Driver,
+        config: RunConfig,
+        modifiers: RunModifiers,
+        width: f32,
+        height: f32,
+    },
+    Pickup {
+        seat: usize,
+        x: f32,
+        y: f32,
+    },
+    // an orb ran over a pickup
+    PickupLost {
+        x: f32,
+        y: f32,
+    },
+    // a life lost, the run's last when lives is 0
+    Death {
+        seat: usize,
+        x: f32,
+        y: f32,
+        orb: u32,
+        orb_x: f32,
+        orb_y: f32,
+        orb_speed: f32,
+        lives: u32,
+    },
+    OrbDestroyed {
+        x: f32,
+        y: f32,
+    },
+    // where a player standing was, every SAMPLE_INTERVAL
+    Position {
+        seat: usize,
+        x: f32,
+        y: f32,
+    },
+    Pause,
+    Resume,
+    Mute,
+    // any results screen, outcome says which
+    RunEnd {
+        outcome: GameState,
+        points: i32,
+    },
+}
+
+// What a line holds around the event.
+#[derive(Serialize)]
+struct Line<'a> {
+    // startup time in unix seconds, tells apart the sessions sharing the file
+    session: u64,
+    run: u32,
+    // seconds into the run
+    time: f32,
+    #[serde(flatten)]
+    event: &'a TelemetryEvent,
+}
+
+#[derive(Resource)]
+pub struct TelemetryLog {
+    file: Option<BufWriter<File>>,
+    session: u64,
+    run: u32,
+}
+
+impl TelemetryLog {
+    pub fn open() -> Self {
+        let file = OpenOptions::new().create(true).append(true).open(TELEMETRY_PATH)
+            .map_err(|e| warn!("no telemetry, could not open {}: {}", TELEMETRY_PATH, e))
+            .ok();
+        TelemetryLog {
+            file: file.map(BufWriter::new),
+            session: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+            run: 0,
+        }
+    }
+}
+
+pub fn sample_positions(
+    time: Res<GameTime>,
+    mut next: Local<f32>,
+    player_query: Query<(&Transform, &Player), Without<Down>>,
+    mut telemetry: EventWriter<TelemetryEvent>,
+) {
+    // the clock went back, a new run
+    if time.value + SAMPLE_INTERVAL < *next {
+        *next = 0.;
+    }
+    if time.value < *next {
+        return;
+    }
+    *next = time.value + SAMPLE_INTERVAL;
+    for (transform, player) in player_query.iter() {
+        telemetry.send(TelemetryEvent::Position {
+            seat: player.seat,
+            x: transform.translation.x,
+            y: transform.translation.y,
+        });
+    }
+}
+
+// Pause and resume, from the state changes rather than from every menu that makes them.
+pub fn track_pause(
+    state: Res<State<GameState>>,
+    mut last: Local<Option<GameState>>,
+    mut telemetry: EventWriter<TelemetryEvent>,
+) {
+    let current = state.current();
+    match (last.as_ref(), current) {
+        (Some(GameState::Game), GameState::Pause) => telemetry.send(TelemetryEvent::Pause),
+        (Some(GameState::Pause), GameState::Game) => telemetry.send(TelemetryEvent::Resume),
+        _ => {}
+    }
+    *last = Some(current.clone());
+}
+
+// Runs on every results screen.
+pub fn log_run_end(
+    state: Res<State<GameState>>,
+    score: Res<Score>,
+    mut telemetry: EventWriter<TelemetryEvent>,
+) {
+    telemetry.send(TelemetryEvent::RunEnd {
+        outcome: state.current().clone(),
+        points: score.points,
+    });
+}
+
+// Stamps and appends the frame's events, after everything that sends them.
+pub fn write_telemetry(
+    time: Res<GameTime>,
+    mut log: ResMut<TelemetryLog>,
+    mut events: EventReader<TelemetryEvent>,
+) {
+    if log.file.is_none() {
+        events.clear();
+        return;
+    }
+    let mut written = false;
+    for event in events.iter() {
+        if matches!(event, TelemetryEvent::RunStart { .. }) {
+            log.run += 1;
+        }
+        let line = Line { session: log.session, run: log.run, time: time.value, event };
+        let mut text = serde_json::to_string(&line).unwrap();
+        text.push('\n');
+        if let Some(file) = log.file.as_mut() {
+            if let Err(e) = file.write_all(text.as_bytes()) {
+                warn!("telemetry stopped, could not write {}: {}", TELEMETRY_PATH, e);
+                log.file = None;
+                return;
+            }
+        }
+        written = true;
+    }
+    if written {
+        if let Some(file) = log.file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
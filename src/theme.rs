@@ -0,0 +1,328 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::{
+    Score,
+    accessibility::{Accessibility, ColorVision},
+    controls::Controls,
+};
+
+// extra themes are picked up from here at startup, one .ron file per theme
+const THEME_DIR: &str = "assets/themes";
+
+// Every color the game draws with. Spawns and recolors read from the active theme
+// instead of using literals.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub text: Color,
+    pub star: Color,
+    pub player_rim: Color,
+    pub player_core: Color,
+    // the second player's core in co-op
+    pub partner_core: Color,
+    pub player_fill: Color,
+    pub pickup: Color,
+    pub pickup_ring: Color,
+    pub pickup_target: Color,
+    pub orb: Color,
+    // orb border while orbs are deadly, fading into `orb_prey` as the goal gets closer
+    pub orb_danger: Color,
+    pub orb_prey: Color,
+    pub spark: Color,
+    pub explosion: Color,
+    pub death_flash: Color,
+    pub goal_flash: Color,
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Theme {
+            name: "Classic".to_string(),
+            background: Color::BLACK,
+            text: Color::rgba(1., 1., 1., 0.5),
+            star: Color::WHITE,
+            player_rim: Color::WHITE,
+            player_core: Color::BLUE,
+            partner_core: Color::rgb(1., 0.45, 0.),
+            player_fill: Color::WHITE,
+            pickup: Color::WHITE,
+            pickup_ring: Color::BLUE,
+            pickup_target: Color::YELLOW,
+            orb: Color::BLACK,
+            orb_danger: Color::RED,
+            orb_prey: Color::BLUE,
+            spark: Color::rgb(1., 1., 0.6),
+            explosion: Color::rgb(1., 0.5, 0.1),
+            death_flash: Color::rgba(1., 0., 0., 0.6),
+            goal_flash: Color::rgba(1., 1., 1., 0.5),
+        }
+    }
+
+    pub fn dusk() -> Self {
+        Theme {
+            name: "Dusk".to_string(),
+            background: Color::rgb(0.06, 0.04, 0.12),
+            text: Color::rgba(1., 0.85, 0.7, 0.6),
+            star: Color::rgb(1., 0.85, 0.75),
+            player_rim: Color::rgb(1., 0.9, 0.8),
+            player_core: Color::rgb(0.45, 0.2, 0.55),
+            partner_core: Color::rgb(0.2, 0.5, 0.45),
+            player_fill: Color::rgb(1., 0.75, 0.45),
+            pickup: Color::rgb(1., 0.9, 0.8),
+            pickup_ring: Color::rgb(0.45, 0.2, 0.55),
+            pickup_target: Color::rgb(1., 0.6, 0.2),
+            orb: Color::rgb(0.03, 0.02, 0.06),
+            orb_danger: Color::rgb(0.95, 0.25, 0.3),
+            orb_prey: Color::rgb(0.4, 0.85, 0.75),
+            spark: Color::rgb(1., 0.75, 0.45),
+            explosion: Color::rgb(0.95, 0.3, 0.35),
+            death_flash: Color::rgba(0.95, 0.25, 0.3, 0.5),
+            goal_flash: Color::rgba(1., 0.85, 0.7, 0.4),
+        }
+    }
+
+    pub fn paper() -> Self {
+        Theme {
+            name: "Paper".to_string(),
+            background: Color::rgb(0.93, 0.91, 0.86),
+            text: Color::rgba(0.1, 0.1, 0.1, 0.7),
+            star: Color::rgb(0.55, 0.52, 0.48),
+            player_rim: Color::rgb(0.1, 0.1, 0.1),
+            player_core: Color::rgb(0.93, 0.91, 0.86),
+            partner_core: Color::rgb(0.8, 0.45, 0.1),
+            player_fill: Color::rgb(0.1, 0.1, 0.1),
+            pickup: Color::rgb(0.1, 0.1, 0.1),
+            pickup_ring: Color::rgb(0.6, 0.6, 0.6),
+            pickup_target: Color::rgb(0.1, 0.45, 0.8),
+            orb: Color::rgb(0.85, 0.83, 0.78),
+            orb_danger: Color::rgb(0.8, 0.15, 0.1),
+            orb_prey: Color::rgb(0.1, 0.55, 0.3),
+            spark: Color::rgb(0.1, 0.45, 0.8),
+            explosion: Color::rgb(0.8, 0.15, 0.1),
+            death_flash: Color::rgba(0.8, 0.15, 0.1, 0.4),
+            goal_flash: Color::rgba(0.1, 0.1, 0.1, 0.3),
+        }
+    }
+
+    // Colorblind palettes keep danger and prey apart in lightness as well as hue,
+    // built from the Okabe-Ito set.
+    pub fn deuteranopia() -> Self {
+        Theme {
+            name: "Deuteranopia".to_string(),
+            orb_danger: Color::rgb(0.9, 0.6, 0.),
+            orb_prey: Color::rgb(0., 0.45, 0.7),
+            pickup_ring: Color::rgb(0., 0.45, 0.7),
+            pickup_target: Color::rgb(0.94, 0.89, 0.26),
+            player_core: Color::rgb(0., 0.45, 0.7),
+            partner_core: Color::rgb(0.8, 0.47, 0.65),
+            spark: Color::rgb(0.94, 0.89, 0.26),
+            explosion: Color::rgb(0.9, 0.6, 0.),
+            death_flash: Color::rgba(0.9, 0.6, 0., 0.5),
+            ..Theme::classic()
+        }
+    }
+
+    pub fn protanopia() -> Self {
+        Theme {
+            name: "Protanopia".to_string(),
+            orb_danger: Color::rgb(0.94, 0.89, 0.26),
+            orb_prey: Color::rgb(0.34, 0.71, 0.91),
+            pickup_ring: Color::rgb(0., 0.45, 0.7),
+            pickup_target: Color::rgb(0.94, 0.89, 0.26),
+            player_core: Color::rgb(0., 0.45, 0.7),
+            partner_core: Color::rgb(0.8, 0.47, 0.65),
+            spark: Color::rgb(0.94, 0.89, 0.26),
+            explosion: Color::rgb(0.94, 0.89, 0.26),
+            death_flash: Color::rgba(0.94, 0.89, 0.26, 0.5),
+            ..Theme::classic()
+        }
+    }
+
+    pub fn tritanopia() -> Self {
+        Theme {
+            name: "Tritanopia".to_string(),
+            orb_danger: Color::rgb(0.84, 0.37, 0.),
+            orb_prey: Color::rgb(0., 0.62, 0.45),
+            pickup_ring: Color::rgb(0.5, 0.5, 0.5),
+            pickup_target: Color::rgb(0.8, 0.47, 0.65),
+            player_core: Color::rgb(0., 0.62, 0.45),
+            partner_core: Color::rgb(0.34, 0.71, 0.91),
+            spark: Color::rgb(0.8, 0.47, 0.65),
+            explosion: Color::rgb(0.84, 0.37, 0.),
+            death_flash: Color::rgba(0.84, 0.37, 0., 0.5),
+            ..Theme::classic()
+        }
+    }
+
+    // pure black and white with one bright accent, text fully opaque
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: "High Contrast".to_string(),
+            background: Color::BLACK,
+            text: Color::WHITE,
+            star: Color::rgb(0.6, 0.6, 0.6),
+            player_rim: Color::WHITE,
+            player_core: Color::BLACK,
+            partner_core: Color::rgb(0.5, 0.5, 0.5),
+            player_fill: Color::WHITE,
+            pickup: Color::WHITE,
+            pickup_ring: Color::rgb(0.5, 0.5, 0.5),
+            pickup_target: Color::YELLOW,
+            orb: Color::BLACK,
+            orb_danger: Color::WHITE,
+            orb_prey: Color::YELLOW,
+            spark: Color::WHITE,
+            explosion: Color::WHITE,
+            death_flash: Color::rgba(1., 1., 1., 0.4),
+            goal_flash: Color::rgba(1., 1., 0., 0.4),
+        }
+    }
+
+    // orb border color for a goal progress between 0 and 1
+    pub fn orb_border(&self, progress: f32) -> Color {
+        lerp_color(self.orb_danger, self.orb_prey, progress.clamp(0., 1.))
+    }
+
+    pub fn paint(&self, paint: Paint, progress: f32) -> Color {
+        match paint {
+            Paint::PlayerRim => self.player_rim,
+            Paint::PlayerCore => self.player_core,
+            Paint::PartnerCore => self.partner_core,
+            Paint::PlayerFill => self.player_fill,
+            Paint::Pickup => self.pickup,
+            Paint::PickupRing => self.pickup_ring,
+            Paint::PickupTarget => self.pickup_target,
+            Paint::Orb => self.orb,
+            Paint::OrbBorder => self.orb_border(progress),
+        }
+    }
+}
+
+// Which theme slot a mesh is colored with, so a theme switch can recolor it in place.
+#[derive(Component, Clone, Copy)]
+pub enum Paint {
+    PlayerRim,
+    PlayerCore,
+    PartnerCore,
+    PlayerFill,
+    Pickup,
+    PickupRing,
+    PickupTarget,
+    Orb,
+    OrbBorder,
+}
+
+// A text drawn plainly in the theme's text color. Texts that fade or highlight color themselves every frame.
+#[derive(Component)]
+pub struct TextPaint;
+
+#[derive(Resource)]
+pub struct ThemeLibrary {
+    pub themes: Vec<Theme>,
+    pub index: usize,
+}
+
+impl ThemeLibrary {
+    // built-in themes followed by whatever parses from THEME_DIR
+    pub fn load() -> Self {
+        let mut themes = vec![Theme::classic(), Theme::dusk(), Theme::paper()];
+
+        if let Ok(entries) = fs::read_dir(THEME_DIR) {
+            let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            for path in paths {
+                if path.extension().and_then(|ext| ext.to_str()) != Some("ron") {
+                    continue;
+                }
+                match fs::read_to_string(&path).map_err(|e| e.to_string())
+                    .and_then(|text| ron::from_str::<Theme>(&text).map_err(|e| e.to_string()))
+                {
+                    Ok(theme) => themes.push(theme),
+                    Err(e) => warn!("skipping theme {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        ThemeLibrary { themes, index: 0 }
+    }
+
+    pub fn current(&self) -> Theme {
+        self.themes[self.index].clone()
+    }
+}
+
+pub fn cycle_theme(
+    input: Res<Input<KeyCode>>,
+    controls: Res<Controls>,
+    mut library: ResMut<ThemeLibrary>,
+) {
+    if input.just_pressed(controls.theme) {
+        library.index = (library.index + 1) % library.themes.len();
+    }
+}
+
+// Accessibility palettes win over the picked theme, high contrast wins over both.
+pub fn sync_theme(
+    library: Res<ThemeLibrary>,
+    accessibility: Res<Accessibility>,
+    mut theme: ResMut<Theme>,
+) {
+    if !library.is_changed() && !accessibility.is_changed() {
+        return;
+    }
+    *theme = if accessibility.high_contrast {
+        Theme::high_contrast()
+    } else {
+        match accessibility.color_vision {
+            ColorVision::Typical => library.current(),
+            ColorVision::Deuteranopia => Theme::deuteranopia(),
+            ColorVision::Protanopia => Theme::protanopia(),
+            ColorVision::Tritanopia => Theme::tritanopia(),
+        }
+    };
+}
+
+// Pushes the active theme onto everything already on screen whenever it changes.
+pub fn apply_theme(
+    theme: Res<Theme>,
+    score: Res<Score>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    paint_query: Query<(&Paint, &Handle<ColorMaterial>)>,
+    mut text_query: Query<&mut Text, With<TextPaint>>,
+) {
+    // orb borders track goal progress, a score change repaints those alone
+    let repaint_all = theme.is_changed();
+    if !repaint_all && !score.is_changed() {
+        return;
+    }
+
+    let progress = score.progress();
+    for (paint, handle) in paint_query.iter() {
+        if !repaint_all && !matches!(paint, Paint::OrbBorder) {
+            continue;
+        }
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = theme.paint(*paint, progress);
+        }
+    }
+    if !repaint_all {
+        return;
+    }
+    clear_color.0 = theme.background;
+    for mut text in text_query.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color = theme.text;
+        }
+    }
+}
+
+pub fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let a = Vec4::from(from.as_rgba_f32());
+    let b = Vec4::from(to.as_rgba_f32());
+    let c = a + (b - a) * t;
+    Color::rgba(c.x, c.y, c.z, c.w)
+}
@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use std::cmp::Reverse;
+
+use crate::{
+    Player, Score,
+    controls::Controls,
+    i18n::Locale,
+    modes::{Players, RunConfig},
+};
+
+// extra push past just touching, in player radii, so a bump reads as one
+const KNOCK: f32 = 0.5;
+
+// Who took the last versus run, filled in as the results screen opens.
+#[derive(Resource, Default)]
+pub struct VersusResult {
+    // None for a draw
+    pub winner: Option<usize>,
+    // (seat, points) by seat
+    standings: Vec<(usize, i32)>,
+}
+
+impl VersusResult {
+    // a plain key, so the title follows language changes like the others
+    pub fn title(&self) -> &'static str {
+        match self.winner {
+            Some(0) => "versus_p1_wins",
+            Some(_) => "versus_p2_wins",
+            None => "versus_draw",
+        }
+    }
+
+    // Still standing beats knocked out, then the hunter, then points. A tie at the top is a draw.
+    fn decide(players: &[&Player], hunter: Option<usize>) -> Self {
+        let rank = |player: &Player| (player.lives > 0, hunter == Some(player.seat), player.points);
+        let mut players = players.to_vec();
+        players.sort_by_key(|player| Reverse(rank(player)));
+
+        let winner = match players.as_slice() {
+            [first, second, ..] if rank(first) == rank(second) => None,
+            [first, ..] => Some(first.seat),
+            [] => None,
+        };
+        let mut standings: Vec<_> = players.iter().map(|player| (player.seat, player.points)).collect();
+        standings.sort();
+        VersusResult { winner, standings }
+    }
+
+    pub fn lines(&self, locale: &Locale, controls: &Controls) -> String {
+        self.standings.iter()
+            .map(|(seat, points)| locale.format("versus_points", controls, &[
+                ("player", (seat + 1).to_string()),
+                ("points", points.to_string()),
+            ]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Pushes two overlapping players apart along the line between them, movement keeps them on screen.
+pub fn bump(a: &mut Transform, b: &mut Transform, radius: f32) {
+    let offset = (a.translation - b.translation).truncate();
+    let direction = if offset == Vec2::ZERO { Vec2::Y } else { offset.normalize() };
+    let push = ((radius * 2. - offset.length()).max(0.) / 2. + radius * KNOCK) * direction;
+    a.translation += push.extend(0.);
+    b.translation -= push.extend(0.);
+}
+
+// Runs before the results title is drawn.
+pub fn decide_winner(
+    config: Res<RunConfig>,
+    score: Res<Score>,
+    mut result: ResMut<VersusResult>,
+    player_query: Query<&Player>,
+) {
+    *result = if config.players == Players::Versus {
+        VersusResult::decide(&player_query.iter().collect::<Vec<_>>(), score.hunter)
+    } else {
+        VersusResult::default()
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(seat: usize, lives: u32, points: i32) -> Player {
+        Player { points, ..Player::new(seat, lives) }
+    }
+
+    #[test]
+    fn last_one_standing_wins() {
+        // more points don't help once out of lives
+        let (first, second) = (player(0, 0, 900), player(1, 1, 100));
+        let result = VersusResult::decide(&[&first, &second], None);
+        assert_eq!(result.winner, Some(1));
+        assert_eq!(result.standings, vec![(0, 900), (1, 100)]);
+    }
+
+    #[test]
+    fn points_decide_between_standing_players() {
+        let (first, second) = (player(0, 2, 300), player(1, 3, 200));
+        assert_eq!(VersusResult::decide(&[&first, &second], None).winner, Some(0));
+    }
+
+    #[test]
+    fn hunter_beats_points() {
+        let (first, second) = (player(0, 1, 300), player(1, 1, 200));
+        assert_eq!(VersusResult::decide(&[&first, &second], Some(1)).winner, Some(1));
+    }
+
+    #[test]
+    fn even_runs_are_draws() {
+        let (first, second) = (player(0, 1, 250), player(1, 2, 250));
+        assert_eq!(VersusResult::decide(&[&first, &second], None).winner, None);
+        // both knocked out in the same frame
+        let (first, second) = (player(0, 0, 400), player(1, 0, 400));
+        assert_eq!(VersusResult::decide(&[&first, &second], None).winner, None);
+    }
+
+    #[test]
+    fn no_players_no_winner() {
+        assert_eq!(VersusResult::decide(&[], None).winner, None);
+    }
+}